@@ -0,0 +1,78 @@
+//! Compares the tree-walking interpreter with the bytecode interpreter.
+//!
+//! Runs the programs in `benches/programs` and any other brainfuck file
+//! given as argument: `cargo bench -- path/to/program.b`.
+
+use std::{
+    fs, io,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use mindfuck::{lower, optimize, parse, Interpreter, Options};
+
+const RUNS: u32 = 5;
+
+fn best_of(mut f: impl FnMut()) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    let mut paths: Vec<PathBuf> =
+        fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/benches/programs"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+    paths.sort();
+    paths.extend(
+        std::env::args()
+            .skip(1)
+            .filter(|arg| !arg.starts_with("--"))
+            .map(PathBuf::from),
+    );
+
+    for path in paths {
+        let source = fs::read_to_string(&path).unwrap();
+        let tokens = optimize(parse(&source).unwrap(), &Options::default());
+        let program = lower(&tokens);
+
+        let tree = best_of(|| {
+            Interpreter::<u8>::default()
+                .run(&tokens, &mut io::empty(), &mut io::sink())
+                .unwrap()
+        });
+        let flat = best_of(|| {
+            Interpreter::<u8>::default()
+                .execute(&program, &mut io::empty(), &mut io::sink())
+                .unwrap()
+        });
+        print!(
+            "{:<20} run: {:>10.3?}  execute: {:>10.3?}  ({:.2}x)",
+            path.file_name().unwrap().to_string_lossy(),
+            tree,
+            flat,
+            tree.as_secs_f64() / flat.as_secs_f64()
+        );
+        #[cfg(feature = "jit")]
+        {
+            let jit = best_of(|| {
+                Interpreter::<u8>::default()
+                    .run_jit(&tokens, &mut io::empty(), &mut io::sink())
+                    .unwrap()
+            });
+            print!(
+                "  jit: {:>10.3?}  ({:.2}x)",
+                jit,
+                tree.as_secs_f64() / jit.as_secs_f64()
+            );
+        }
+        println!();
+    }
+}
@@ -0,0 +1,96 @@
+//! Compares byte at a time I/O with buffered I/O on large piped inputs.
+//!
+//! The unbuffered run reads the input with a `read` call per `,` and flushes
+//! a line buffered output before each of them, like the command line used to.
+//! The buffered run reads and writes through buffers flushed only at exit,
+//! like the command line does now when the input is not a terminal.
+
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter, LineWriter, Read, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use mindfuck::{lower, optimize, parse, EofPolicy, Flush, Interpreter, Options, Program};
+
+const RUNS: u32 = 5;
+/// Size of the generated input.
+const INPUT: usize = 1 << 20;
+
+/// Name, source and EOF policy of the filters.
+const PROGRAMS: [(&str, &str, EofPolicy); 2] = [
+    ("cat", ",[.,]", EofPolicy::Zero),
+    (
+        "rot13",
+        "-,+[-[>>++++[>++++++++<-]<+<-[>+>+>-[>>>]<[[>+<-]>>+>]<<<<<-]]>>>[-]+>--[-[<->+++[-]]]<[\
+         ++++++++++++<[>-[>+>>]>[+[<+>-]>+>>]<<<<<-]>>[<+>-]>[-[-<<[-]>>]<<[<<->>-]>>]<<[<<+>>-]]\
+         <[-]<.[-]<-,+]",
+        EofPolicy::Unchanged,
+    ),
+];
+
+fn best_of(mut f: impl FnMut()) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn run<R: Read, W: Write>(program: &Program, options: Options, mut input: R, mut output: W) {
+    Interpreter::<u8>::new(options)
+        .execute(program, &mut input, &mut output)
+        .unwrap();
+    output.flush().unwrap();
+}
+
+fn main() {
+    let directory = std::env::temp_dir();
+    let input_path = directory.join("mindfuck-io-input.txt");
+    let output_path = directory.join("mindfuck-io-output.txt");
+    let text = b"The quick brown fox jumps over the lazy dog.\n";
+    let input: Vec<u8> = text.iter().copied().cycle().take(INPUT).collect();
+    fs::write(&input_path, input).unwrap();
+    let open = |path: &Path| File::open(path).unwrap();
+    let create = |path: &Path| File::create(path).unwrap();
+
+    for (name, source, eof) in PROGRAMS {
+        let options = |flush| Options {
+            eof,
+            flush,
+            ..Options::default()
+        };
+        let program = lower(&optimize(parse(source).unwrap(), &Options::default()));
+
+        let unbuffered = best_of(|| {
+            run(
+                &program,
+                options(Flush::BeforeInput),
+                open(&input_path),
+                LineWriter::new(create(&output_path)),
+            )
+        });
+        let buffered = best_of(|| {
+            run(
+                &program,
+                options(Flush::AtExit),
+                BufReader::new(open(&input_path)),
+                BufWriter::new(create(&output_path)),
+            )
+        });
+        println!(
+            "{:<10} {} KiB  unbuffered: {:>10.3?}  buffered: {:>10.3?}  ({:.2}x)",
+            name,
+            INPUT / 1024,
+            unbuffered,
+            buffered,
+            unbuffered.as_secs_f64() / buffered.as_secs_f64()
+        );
+    }
+    fs::remove_file(input_path).unwrap();
+    fs::remove_file(output_path).unwrap();
+}
@@ -0,0 +1,68 @@
+Towers of Hanoi
+
+Prints the 262143 moves that take 18 disks from peg A to peg C
+A binary counter picks the disk of each move and every disk cycles through
+the pegs in a fixed direction
+
+>>>>>>>>>>>>[-]+>>>[-]+>>[-]+++++++++++++++++++++++++++++++++++++++++++++++++>>>
+>>>>[-]+>>>[-]++>>[-]++++++++++++++++++++++++++++++++++++++++++++++++++>>>>>>>[-
+]+>>>[-]+>>[-]+++++++++++++++++++++++++++++++++++++++++++++++++++>>>>>>>[-]+>>>[
+-]++>>[-]++++++++++++++++++++++++++++++++++++++++++++++++++++>>>>>>>[-]+>>>[-]+>
+>[-]+++++++++++++++++++++++++++++++++++++++++++++++++++++>>>>>>>[-]+>>>[-]++>>[-
+]++++++++++++++++++++++++++++++++++++++++++++++++++++++>>>>>>>[-]+>>>[-]+>>[-]++
++++++++++++++++++++++++++++++++++++++++++++++++++++++>>>>>>>[-]+>>>[-]++>>[-]+++
++++++++++++++++++++++++++++++++++++++++++++++++++++++>>>>>>>[-]+>>>[-]+>>[-]++++
++++++++++++++++++++++++++++++++++++++++++++++++++++++>>>>>>>[-]+>>>[-]++>[-]++++
++++++++++++++++++++++++++++++++++++++++++++++>[-]+++++++++++++++++++++++++++++++
++++++++++++++++++>>>>>>>[-]+>>>[-]+>[-]+++++++++++++++++++++++++++++++++++++++++
+++++++++>[-]+++++++++++++++++++++++++++++++++++++++++++++++++>>>>>>>[-]+>>>[-]++
+>[-]+++++++++++++++++++++++++++++++++++++++++++++++++>[-]+++++++++++++++++++++++
++++++++++++++++++++++++++++>>>>>>>[-]+>>>[-]+>[-]+++++++++++++++++++++++++++++++
+++++++++++++++++++>[-]+++++++++++++++++++++++++++++++++++++++++++++++++++>>>>>>>
+[-]+>>>[-]++>[-]+++++++++++++++++++++++++++++++++++++++++++++++++>[-]+++++++++++
++++++++++++++++++++++++++++++++++++++++++>>>>>>>[-]+>>>[-]+>[-]+++++++++++++++++
+++++++++++++++++++++++++++++++++>[-]++++++++++++++++++++++++++++++++++++++++++++
++++++++++>>>>>>>[-]+>>>[-]++>[-]++++++++++++++++++++++++++++++++++++++++++++++++
++>[-]++++++++++++++++++++++++++++++++++++++++++++++++++++++>>>>>>>[-]+>>>[-]+>[-
+]+++++++++++++++++++++++++++++++++++++++++++++++++>[-]++++++++++++++++++++++++++
++++++++++++++++++++++++++++++>>>>>>>[-]+>>>[-]++>[-]++++++++++++++++++++++++++++
++++++++++++++++++++++>[-]+++++++++++++++++++++++++++++++++++++++++++++++++++++++
++<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<[-]+[>>>>>>>>>>>[->>
+>>>>>>>>>>]+<[->>>>>>+>+<<<<<<<]>>>>>>>[-<<<<<<<+>>>>>>>]<[>[-]+++++++++++++++++
+++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++.+++++++++++++++++++
++++++++++++++++.+++++++.-----------------.--------------------------------------
+-------------------------------.++++++++++++++++++++++++++++++++++++++++++++++++
+++++++++++++++++++++.+++++.++++++++++.--------.---------------------------------
+------------------------------------------.[-][-]<<<[->>>+>+<<<<]>>>>[-<<<<+>>>>
+]<[.[-]]<<.>>[-]++++++++++++++++++++++++++++++++.+++++++++++++++++++++++++++++++
++++++++++++++++++++++++++++++++++++++++.++++++++++++.---.--.--------------------
+---------------------------------------------------------.[-][-]<<<<<[->>>>>+>+<
+<<<<<]>>>>>>[-<<<<<<+>>>>>>]<+++++++++++++++++++++++++++++++++++++++++++++++++++
+++++++++++++++.[-]<<<<[-<+>>>>>+<<<<]>>>>[-<<<<+>>>>][-]<<<<<[->>>>>+>+<<<<<<]>>
+>>>>[-<<<<<<+>>>>>>]<---[->+>+<<]>>[-<<+>>][-]+<[>[-]<[-]]>[<<<<<<<[-]>>>>>>>[-]
+]<<[-][-]<<<<<[->>>>>+>+<<<<<<]>>>>>>[-<<<<<<+>>>>>>]<----[->+>+<<]>>[-<<+>>][-]
++<[>[-]<[-]]>[<<<<<<<[-]+>>>>>>>[-]]<<[-][-]++++++++++++++++++++++++++++++++.+++
+++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++
++.-----.------------------------------------------------------------------------
+-------.[-][-]<<<<<[->>>>>+>+<<<<<<]>>>>>>[-<<<<<<+>>>>>>]<+++++++++++++++++++++
+++++++++++++++++++++++++++++++++++++++++++++.[-][-]++++++++++.[-]<[-]]<<<<<<<<<<
+<<<<<<<<[<<<<<<<<<<<<]>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>[-<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<+>+>
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>]<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<[->>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>>
+>>>>>>>>>>>>>>>>>>>>>>>>>>>>+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
+<<<<<<<<<<<]<[<<<<[-]>>>>[-]]<<<<]
@@ -0,0 +1,259 @@
+use crate::{
+    config::{CellWidth, EdgePolicy, EofPolicy, Flush, Options, OutputMode, Overflow, Tape},
+    token::{walk, Token, TokenKind, Visit},
+};
+
+use super::Emitter;
+
+const INCLUDES: &str = "\
+/* Generated by mindfuck */
+#include <limits.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+";
+
+const PRELUDE: &str = "
+static cell *tape;
+static size_t len;
+static size_t dp;
+static size_t origin;
+
+/* Stops the program with an error about the cell at `index` */
+static void fail(const char *message, ptrdiff_t index) {
+    fflush(stdout);
+    fprintf(stderr, \"\\n\\n[RUNTIME ERROR] %s%td\\n\", message, index - (ptrdiff_t)origin);
+    exit(1);
+}
+
+static inline void grow_right(size_t index) {
+    if (index < len) return;
+    size_t new_len = index + 1 > len * 2 ? index + 1 : len * 2;
+    tape = realloc(tape, new_len * sizeof(cell));
+    if (!tape) fail(\"out of memory growing the tape: \", (ptrdiff_t)index);
+    memset(tape + len, 0, (new_len - len) * sizeof(cell));
+    len = new_len;
+}
+";
+
+fn locate(options: &Options) -> String {
+    let edge = match (options.edge, options.tape) {
+        (EdgePolicy::Wrap, Tape::Fixed(_)) => {
+            "index %= (ptrdiff_t)len;
+    return index < 0 ? index + len : index;"
+        }
+        (EdgePolicy::Clamp, _) => "return v < 0 ? 0 : len - 1;",
+        (EdgePolicy::Wrap | EdgePolicy::Error, _) => {
+            "fail(\"the data pointer moved out of the tape: \", index < 0 ? -1 : (ptrdiff_t)len);
+    return 0;"
+        }
+    };
+    let body = match options.tape {
+        Tape::Fixed(_) => format!(
+            "if (index >= 0 && index < (ptrdiff_t)len) return index;
+    {edge}"
+        ),
+        Tape::Unbounded => format!(
+            "if (index >= 0) {{
+        grow_right(index);
+        return index;
+    }}
+    {edge}"
+        ),
+        Tape::Bidirectional => "if (index >= 0) {
+        grow_right(index);
+        return index;
+    }
+    size_t missing = -index;
+    size_t grow = missing > len ? missing : len;
+    tape = realloc(tape, (len + grow) * sizeof(cell));
+    if (!tape) fail(\"out of memory growing the tape: \", index);
+    memmove(tape + grow, tape, len * sizeof(cell));
+    memset(tape, 0, grow * sizeof(cell));
+    len += grow;
+    origin += grow;
+    dp += grow;
+    return grow - missing;"
+            .to_string(),
+    };
+    format!(
+        "
+/* Index of the cell v cells away from the data pointer */
+static inline size_t locate(ptrdiff_t v) {{
+    ptrdiff_t index = (ptrdiff_t)dp + v;
+    {body}
+}}
+"
+    )
+}
+
+fn arithmetic(options: &Options) -> String {
+    let add = match options.overflow {
+        Overflow::Wrap => "tape[i] += (cell)v;",
+        Overflow::Saturate => {
+            "long long sum = (long long)tape[i] + v;
+    tape[i] = sum < 0 ? 0 : sum > CELL_MAX ? CELL_MAX : (cell)sum;"
+        }
+        Overflow::Trap => {
+            "long long sum = (long long)tape[i] + v;
+    if (sum < 0 || sum > CELL_MAX) fail(\"overflow of the cell \", (ptrdiff_t)i);
+    tape[i] = (cell)sum;"
+        }
+    };
+    let product = match options.overflow {
+        Overflow::Wrap => {
+            "(long long)(cell)((unsigned long long)value * (unsigned long long)factor)"
+        }
+        _ => {
+            "factor > LLONG_MAX / (long long)value ? LLONG_MAX
+        : factor < LLONG_MIN / (long long)value ? LLONG_MIN
+        : (long long)value * factor"
+        }
+    };
+    format!(
+        "
+static inline void add(size_t i, long long v) {{
+    {add}
+}}
+
+static inline void set(size_t i, long long v) {{
+    tape[i] = 0;
+    add(i, v);
+}}
+
+static inline void mul_add(ptrdiff_t offset, long long factor) {{
+    size_t i = locate(offset);
+    cell value = tape[dp];
+    add(i, {product});
+}}
+"
+    )
+}
+
+fn io(options: &Options) -> String {
+    let output = match options.output {
+        OutputMode::Byte => "putchar((unsigned char)value);",
+        OutputMode::Decimal => "printf(\"%lu\\n\", (unsigned long)value);",
+        OutputMode::Utf8 => {
+            "uint32_t c = value;
+    if (c > 0x10FFFF || (c >= 0xD800 && c <= 0xDFFF)) c = 0xFFFD;
+    if (c < 0x80) {
+        putchar(c);
+    } else if (c < 0x800) {
+        putchar(0xC0 | c >> 6);
+        putchar(0x80 | (c & 0x3F));
+    } else if (c < 0x10000) {
+        putchar(0xE0 | c >> 12);
+        putchar(0x80 | (c >> 6 & 0x3F));
+        putchar(0x80 | (c & 0x3F));
+    } else {
+        putchar(0xF0 | c >> 18);
+        putchar(0x80 | (c >> 12 & 0x3F));
+        putchar(0x80 | (c >> 6 & 0x3F));
+        putchar(0x80 | (c & 0x3F));
+    }"
+        }
+    };
+    let eof = match options.eof {
+        EofPolicy::Zero => "tape[i] = 0;",
+        EofPolicy::MinusOne => "tape[i] = CELL_MAX;",
+        EofPolicy::Unchanged => "return;",
+    };
+    let flush = match options.flush {
+        Flush::BeforeInput => "fflush(stdout);\n    ",
+        Flush::AtExit => "",
+    };
+    format!(
+        "
+static inline void out(cell value) {{
+    {output}
+}}
+
+static inline void in(size_t i) {{
+    {flush}int c = getchar();
+    if (c == EOF) {{
+        {eof}
+    }} else {{
+        tape[i] = (cell)c;
+    }}
+}}
+"
+    )
+}
+
+fn tokens(emitter: &mut Emitter, tokens: &[Token]) {
+    for visit in walk(tokens) {
+        match visit {
+            Visit::Open(_) => {
+                emitter.line("while (tape[dp]) {");
+                emitter.indent += 1;
+            }
+            Visit::Close(_) => {
+                emitter.indent -= 1;
+                emitter.line("}");
+            }
+            Visit::Token(token) => match &token.kind {
+                TokenKind::Output => emitter.line("out(tape[dp]);"),
+                TokenKind::Input => emitter.line("in(dp);"),
+                TokenKind::Move(v) => emitter.line(&format!("dp = locate({v});")),
+                TokenKind::IncValue(v) => emitter.line(&format!("add(dp, {v}LL);")),
+                TokenKind::SetZero => emitter.line("tape[dp] = 0;"),
+                TokenKind::Scan(step) => {
+                    emitter.line(&format!("while (tape[dp]) dp = locate({step});"))
+                }
+                TokenKind::MulAdd { offset, factor } => {
+                    emitter.line(&format!("if (tape[dp]) mul_add({offset}, {factor}LL);"))
+                }
+                TokenKind::Add { offset, value } => {
+                    emitter.line(&format!("add(locate({offset}), {value}LL);"))
+                }
+                TokenKind::Set { offset, value } => {
+                    emitter.line(&format!("set(locate({offset}), {value}LL);"))
+                }
+                // `locate` can move the tape, so it runs before `tape` is read
+                TokenKind::OutputAt { offset } => {
+                    emitter.line(&format!("{{ size_t i = locate({offset}); out(tape[i]); }}"))
+                }
+                TokenKind::InputAt { offset } => emitter.line(&format!("in(locate({offset}));")),
+                // Walked as the `[` and the `]`
+                TokenKind::Loop(_) => unreachable!(),
+            },
+        }
+    }
+}
+
+/// Translates the tokens to a standalone C program with the same tape, cell
+/// and I/O behavior as an [`Interpreter`](crate::Interpreter) with `options`.
+pub fn emit_c(program: &[Token], options: &Options) -> String {
+    let (cell, max) = match options.cell {
+        CellWidth::U8 => ("uint8_t", "UINT8_MAX"),
+        CellWidth::U16 => ("uint16_t", "UINT16_MAX"),
+        CellWidth::U32 => ("uint32_t", "UINT32_MAX"),
+    };
+    let size = match options.tape {
+        Tape::Fixed(size) => size,
+        Tape::Unbounded | Tape::Bidirectional => 1,
+    };
+
+    let mut emitter = Emitter::default();
+    emitter.code.push_str(INCLUDES);
+    emitter
+        .code
+        .push_str(&format!("\ntypedef {cell} cell;\n#define CELL_MAX {max}\n"));
+    emitter.code.push_str(PRELUDE);
+    emitter.code.push_str(&locate(options));
+    emitter.code.push_str(&arithmetic(options));
+    emitter.code.push_str(&io(options));
+    emitter.code.push_str("\nint main(void) {\n");
+    emitter.indent = 1;
+    emitter.line(&format!("len = {size};"));
+    emitter.line("tape = calloc(len, sizeof(cell));");
+    emitter.line("if (!tape) fail(\"out of memory allocating the tape: \", 0);");
+    self::tokens(&mut emitter, program);
+    emitter.line("fflush(stdout);");
+    emitter.line("return 0;");
+    emitter.code.push_str("}\n");
+    emitter.code
+}
@@ -0,0 +1,214 @@
+use crate::{
+    config::{CellWidth, EdgePolicy, EofPolicy, Options, OutputMode, Overflow, Tape},
+    error::Error,
+    token::Token,
+};
+
+use super::x86_64::{Assembler, Routine, Routines};
+
+/// Load address of the executable.
+const BASE: u64 = 0x400000;
+const PAGE: u64 = 0x1000;
+/// ELF header plus two program headers, the code follows them.
+const HEADERS: u64 = 64 + 2 * 56;
+
+const OUT_OF_TAPE: &[u8] = b"\n\n[RUNTIME ERROR] the data pointer moved out of the tape\n";
+
+/// `write(1, rsi, 1)`, al is 0 if the byte was written.
+fn write_byte(asm: &mut Assembler) {
+    asm.emit(&[0xba, 0x01, 0x00, 0x00, 0x00]); // mov edx, 1
+    write_stdout(asm);
+}
+
+/// `write(1, rsi, rdx)`, al is 0 if something was written.
+fn write_stdout(asm: &mut Assembler) {
+    asm.emit(&[0xb8, 0x01, 0x00, 0x00, 0x00]); // mov eax, 1
+    asm.emit(&[0xbf, 0x01, 0x00, 0x00, 0x00]); // mov edi, 1
+    asm.emit(&[0x0f, 0x05]); // syscall
+    asm.emit(&[0x48, 0x85, 0xc0]); // test rax, rax
+    asm.emit(&[0x0f, 0x9e, 0xc0]); // setle al
+}
+
+fn output_routine(asm: &mut Assembler, mode: OutputMode) {
+    if mode == OutputMode::Utf8 {
+        // Cells >= 0x80 are encoded as two bytes pushed on the stack
+        asm.emit(&[0x0f, 0xb6, 0x06]); // movzx eax, byte [rsi]
+        asm.emit(&[0x3c, 0x80]); // cmp al, 0x80
+        asm.emit(&[0x72, 0x00]); // jb single
+        let single = asm.code.len();
+        asm.emit(&[0x89, 0xc1]); // mov ecx, eax
+        asm.emit(&[0xc1, 0xe9, 0x06]); // shr ecx, 6
+        asm.emit(&[0x81, 0xc9, 0xc0, 0x00, 0x00, 0x00]); // or ecx, 0xc0
+        asm.emit(&[0x83, 0xe0, 0x3f]); // and eax, 0x3f
+        asm.emit(&[0x0d, 0x80, 0x00, 0x00, 0x00]); // or eax, 0x80
+        asm.emit(&[0xc1, 0xe0, 0x08]); // shl eax, 8
+        asm.emit(&[0x09, 0xc8]); // or eax, ecx
+        asm.emit(&[0x50]); // push rax
+        asm.emit(&[0x48, 0x89, 0xe6]); // mov rsi, rsp
+        asm.emit(&[0xba, 0x02, 0x00, 0x00, 0x00]); // mov edx, 2
+        write_stdout(asm);
+        asm.emit(&[0x59]); // pop rcx
+        asm.emit(&[0xc3]); // ret
+        asm.code[single - 1] = (asm.code.len() - single) as u8;
+    }
+    write_byte(asm);
+    asm.emit(&[0xc3]); // ret
+}
+
+fn input_routine(asm: &mut Assembler, eof: EofPolicy) {
+    asm.emit(&[0x49, 0x89, 0xf7]); // mov r15, rsi
+    asm.emit(&[0x31, 0xc0]); // xor eax, eax
+    asm.emit(&[0x31, 0xff]); // xor edi, edi
+    asm.emit(&[0xba, 0x01, 0x00, 0x00, 0x00]); // mov edx, 1
+    asm.emit(&[0x0f, 0x05]); // syscall
+    asm.emit(&[0x48, 0x85, 0xc0]); // test rax, rax
+    let eof = match eof {
+        EofPolicy::Zero => &[0x41, 0xc6, 0x07, 0x00][..], // mov byte [r15], 0
+        EofPolicy::MinusOne => &[0x41, 0xc6, 0x07, 0xff][..], // mov byte [r15], 0xff
+        EofPolicy::Unchanged => &[][..],
+    };
+    asm.emit(&[0x78, 2 + eof.len() as u8 + 3]); // js fail
+    asm.emit(&[0x75, eof.len() as u8]); // jnz ok
+    asm.emit(eof);
+    asm.emit(&[0x31, 0xc0]); // ok: xor eax, eax
+    asm.emit(&[0xc3]); // ret
+    asm.emit(&[0xb0, 0x01]); // fail: mov al, 1
+    asm.emit(&[0xc3]); // ret
+}
+
+fn locate_routine(asm: &mut Assembler, edge: EdgePolicy) {
+    match edge {
+        EdgePolicy::Wrap => {
+            asm.emit(&[0x48, 0x89, 0xf0]); // mov rax, rsi
+            asm.emit(&[0x4c, 0x29, 0xe0]); // sub rax, r12
+            asm.emit(&[0x4c, 0x89, 0xe9]); // mov rcx, r13
+            asm.emit(&[0x4c, 0x29, 0xe1]); // sub rcx, r12
+            asm.emit(&[0x48, 0x99]); // cqo
+            asm.emit(&[0x48, 0xf7, 0xf9]); // idiv rcx
+            asm.emit(&[0x48, 0x85, 0xd2]); // test rdx, rdx
+            asm.emit(&[0x79, 0x03]); // jns +3
+            asm.emit(&[0x48, 0x01, 0xca]); // add rdx, rcx
+            asm.emit(&[0x49, 0x8d, 0x04, 0x14]); // lea rax, [r12 + rdx]
+            asm.emit(&[0xc3]); // ret
+        }
+        EdgePolicy::Clamp => {
+            asm.emit(&[0x4c, 0x39, 0xe6]); // cmp rsi, r12
+            asm.emit(&[0x73, 0x04]); // jae high
+            asm.emit(&[0x4c, 0x89, 0xe0]); // mov rax, r12
+            asm.emit(&[0xc3]); // ret
+            asm.emit(&[0x49, 0x8d, 0x45, 0xff]); // high: lea rax, [r13 - 1]
+            asm.emit(&[0xc3]); // ret
+        }
+        EdgePolicy::Error => {
+            asm.emit(&[0x48, 0x8d, 0x35]); // lea rsi, [rip + message]
+            let message = asm.code.len();
+            asm.emit(&[0; 4]);
+            asm.emit(&[0xba]); // mov edx, imm32
+            asm.emit(&(OUT_OF_TAPE.len() as u32).to_le_bytes());
+            asm.emit(&[0xb8, 0x01, 0x00, 0x00, 0x00]); // mov eax, 1
+            asm.emit(&[0xbf, 0x02, 0x00, 0x00, 0x00]); // mov edi, 2
+            asm.emit(&[0x0f, 0x05]); // syscall
+            asm.emit(&[0x31, 0xc0]); // xor eax, eax
+            asm.emit(&[0xc3]); // ret
+            let len = asm.code.len();
+            asm.patch(message, len);
+            asm.emit(OUT_OF_TAPE);
+        }
+    }
+}
+
+fn program_header(elf: &mut Vec<u8>, flags: u32, vaddr: u64, file_size: u64, memory_size: u64) {
+    elf.extend_from_slice(&1u32.to_le_bytes()); // PT_LOAD
+    elf.extend_from_slice(&flags.to_le_bytes());
+    elf.extend_from_slice(&0u64.to_le_bytes()); // offset
+    elf.extend_from_slice(&vaddr.to_le_bytes());
+    elf.extend_from_slice(&vaddr.to_le_bytes()); // physical address
+    elf.extend_from_slice(&file_size.to_le_bytes());
+    elf.extend_from_slice(&memory_size.to_le_bytes());
+    elf.extend_from_slice(&PAGE.to_le_bytes()); // align
+}
+
+/// Compiles the tokens to a static x86-64 Linux executable that uses raw
+/// syscalls and keeps the tape in its bss.
+///
+/// Only 8 bit wrapping cells on a fixed tape, written as bytes or UTF-8, are
+/// supported.
+pub fn build_elf(program: &[Token], options: &Options) -> Result<Vec<u8>, Error> {
+    let size = match options.tape {
+        Tape::Fixed(size) => size as u64,
+        _ => return Err(Error::Unsupported("only fixed size tapes can be built")),
+    };
+    if options.cell != CellWidth::U8 || options.overflow != Overflow::Wrap {
+        return Err(Error::Unsupported("only 8 bit wrapping cells can be built"));
+    }
+    if options.output == OutputMode::Decimal {
+        return Err(Error::Unsupported("decimal output can't be built"));
+    }
+
+    let mut asm = Assembler::new(Routines {
+        output: Routine::Local(0),
+        input: Routine::Local(0),
+        locate: Routine::Local(0),
+    });
+    let output = asm.code.len();
+    output_routine(&mut asm, options.output);
+    let input = asm.code.len();
+    input_routine(&mut asm, options.eof);
+    let locate = asm.code.len();
+    locate_routine(&mut asm, options.edge);
+    asm.routines = Routines {
+        output: Routine::Local(output),
+        input: Routine::Local(input),
+        locate: Routine::Local(locate),
+    };
+
+    let start = asm.code.len();
+    let bounds = start_code(&mut asm, program)?;
+    // The code is placed right after the headers, the tape on the next free page
+    let code_size = asm.code.len() as u64;
+    let tape = (BASE + HEADERS + code_size).div_ceil(PAGE) * PAGE;
+    asm.code[bounds..bounds + 8].copy_from_slice(&tape.to_le_bytes());
+    asm.code[bounds + 10..bounds + 18].copy_from_slice(&(tape + size).to_le_bytes());
+
+    let file_size = HEADERS + code_size;
+    let mut elf = Vec::with_capacity(file_size as usize);
+    elf.extend_from_slice(&[0x7f, b'E', b'L', b'F', 2, 1, 1, 0]); // 64 bit, little endian, SysV
+    elf.extend_from_slice(&[0; 8]);
+    elf.extend_from_slice(&2u16.to_le_bytes()); // ET_EXEC
+    elf.extend_from_slice(&0x3eu16.to_le_bytes()); // x86-64
+    elf.extend_from_slice(&1u32.to_le_bytes()); // version
+    elf.extend_from_slice(&(BASE + HEADERS + start as u64).to_le_bytes()); // entry point
+    elf.extend_from_slice(&64u64.to_le_bytes()); // program headers offset
+    elf.extend_from_slice(&0u64.to_le_bytes()); // section headers offset
+    elf.extend_from_slice(&0u32.to_le_bytes()); // flags
+    elf.extend_from_slice(&64u16.to_le_bytes()); // ELF header size
+    elf.extend_from_slice(&56u16.to_le_bytes()); // program header size
+    elf.extend_from_slice(&2u16.to_le_bytes()); // program headers
+    elf.extend_from_slice(&[0; 6]); // no section headers
+    program_header(&mut elf, 5, BASE, file_size, file_size); // R X
+    program_header(&mut elf, 6, tape, 0, size); // R W
+    elf.extend_from_slice(&asm.code);
+    Ok(elf)
+}
+
+/// Emits the entry point: sets up the tape registers, runs the program and
+/// exits. Returns the position of the tape start, the end follows 10 bytes later.
+fn start_code(asm: &mut Assembler, program: &[Token]) -> Result<usize, Error> {
+    asm.emit(&[0x49, 0xbc]); // mov r12, imm64
+    let bounds = asm.code.len();
+    asm.emit(&[0; 8]);
+    asm.emit(&[0x49, 0xbd]); // mov r13, imm64
+    asm.emit(&[0; 8]);
+    asm.emit(&[0x4c, 0x89, 0xe3]); // mov rbx, r12
+    asm.tokens(program)
+        .ok_or(Error::Unsupported("offsets must fit in 32 bits"))?;
+    asm.emit(&[0xb8, 0x3c, 0x00, 0x00, 0x00]); // mov eax, 60
+    asm.emit(&[0x31, 0xff]); // xor edi, edi
+    asm.emit(&[0x0f, 0x05]); // syscall
+    let error = asm.code.len();
+    asm.emit(&[0xb8, 0x3c, 0x00, 0x00, 0x00]); // mov eax, 60
+    asm.emit(&[0xbf, 0x01, 0x00, 0x00, 0x00]); // mov edi, 1
+    asm.emit(&[0x0f, 0x05]); // syscall
+    asm.patch_errors(error);
+    Ok(bounds)
+}
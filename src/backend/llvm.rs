@@ -0,0 +1,487 @@
+use crate::{
+    config::{CellWidth, EdgePolicy, EofPolicy, Flush, Options, OutputMode, Overflow, Tape},
+    error::Error,
+    token::{walk, Token, TokenKind, Visit},
+};
+
+use super::Emitter;
+
+const PRELUDE: &str = "
+declare i32 @putchar(i32)
+declare i32 @getchar()
+declare i32 @fflush(ptr)
+declare i32 @dprintf(i32, ptr, ...)
+declare i32 @printf(ptr, ...)
+declare void @exit(i32)
+declare { i64, i1 } @llvm.smul.with.overflow.i64(i64, i64)
+
+@.format = private constant [26 x i8] c\"\\0A\\0A[RUNTIME ERROR] %s%lld\\0A\\00\"
+@.out_of_tape = private constant [41 x i8] c\"the data pointer moved out of the tape: \\00\"
+@.overflow = private constant [22 x i8] c\"overflow of the cell \\00\"
+@.decimal = private constant [4 x i8] c\"%u\\0A\\00\"
+
+; Stops the program with an error about the cell at `index`
+define internal void @fail(ptr %message, i64 %index) noreturn {
+  call i32 @fflush(ptr null)
+  %format = getelementptr inbounds [26 x i8], ptr @.format, i64 0, i64 0
+  call i32 (i32, ptr, ...) @dprintf(i32 2, ptr %format, ptr %message, i64 %index)
+  call void @exit(i32 1)
+  unreachable
+}
+";
+
+/// Integer cast from `from` to `to` bits, a no-op bitcast if they are the same.
+fn cast(value: &str, from: u32, to: u32, signed: bool) -> String {
+    let op = match from.cmp(&to) {
+        std::cmp::Ordering::Equal => "bitcast",
+        std::cmp::Ordering::Greater => "trunc",
+        std::cmp::Ordering::Less if signed => "sext",
+        std::cmp::Ordering::Less => "zext",
+    };
+    format!("{op} i{from} {value} to i{to}")
+}
+
+/// Shape of the tape shared by the helpers.
+struct Layout {
+    bits: u32,
+    len: usize,
+}
+
+impl Layout {
+    fn cell(&self) -> String {
+        format!("i{}", self.bits)
+    }
+
+    /// Pointer to the cell at `index`.
+    fn pointer(&self, index: &str) -> String {
+        format!(
+            "getelementptr inbounds [{len} x {cell}], ptr @tape, i64 0, i64 {index}",
+            len = self.len,
+            cell = self.cell()
+        )
+    }
+
+    fn max(&self) -> u64 {
+        (1u64 << self.bits) - 1
+    }
+}
+
+fn locate(layout: &Layout, edge: EdgePolicy) -> String {
+    let len = layout.len;
+    let outside = match edge {
+        EdgePolicy::Wrap => format!(
+            "%rem = srem i64 %index, {len}
+  %negative = icmp slt i64 %rem, 0
+  %wrapped = add i64 %rem, {len}
+  %result = select i1 %negative, i64 %wrapped, i64 %rem
+  ret i64 %result"
+        ),
+        EdgePolicy::Clamp => format!(
+            "%negative = icmp slt i64 %index, 0
+  %result = select i1 %negative, i64 0, i64 {}
+  ret i64 %result",
+            len - 1
+        ),
+        EdgePolicy::Error => format!(
+            "%negative = icmp slt i64 %index, 0
+  %edge = select i1 %negative, i64 -1, i64 {len}
+  %message = getelementptr inbounds [41 x i8], ptr @.out_of_tape, i64 0, i64 0
+  call void @fail(ptr %message, i64 %edge)
+  unreachable"
+        ),
+    };
+    format!(
+        "
+; Index of the cell `v` cells away from `dp`
+define internal i64 @locate(i64 %dp, i64 %v) {{
+  %index = add i64 %dp, %v
+  %inside = icmp ult i64 %index, {len}
+  br i1 %inside, label %in_tape, label %out_of_tape
+in_tape:
+  ret i64 %index
+out_of_tape:
+  {outside}
+}}
+"
+    )
+}
+
+fn arithmetic(layout: &Layout, overflow: Overflow) -> String {
+    let cell = layout.cell();
+    let bits = layout.bits;
+    let pointer = layout.pointer("%i");
+    let max = layout.max();
+    let add = match overflow {
+        Overflow::Wrap => format!(
+            "%delta = {}
+  %sum = add {cell} %value, %delta
+  store {cell} %sum, ptr %pointer
+  ret void",
+            cast("%v", 64, bits, true)
+        ),
+        _ => {
+            let clamp = if overflow == Overflow::Saturate {
+                format!(
+                    "%high = select i1 %above, i128 {max}, i128 %wide_sum
+  %clamped = select i1 %below, i128 0, i128 %high
+  %sum = trunc i128 %clamped to {cell}
+  store {cell} %sum, ptr %pointer
+  ret void"
+                )
+            } else {
+                format!(
+                    "%outside = or i1 %below, %above
+  br i1 %outside, label %overflow, label %store
+overflow:
+  %message = getelementptr inbounds [22 x i8], ptr @.overflow, i64 0, i64 0
+  call void @fail(ptr %message, i64 %i)
+  unreachable
+store:
+  %sum = trunc i128 %wide_sum to {cell}
+  store {cell} %sum, ptr %pointer
+  ret void"
+                )
+            };
+            format!(
+                "%wide_value = zext {cell} %value to i128
+  %wide_v = sext i64 %v to i128
+  %wide_sum = add i128 %wide_value, %wide_v
+  %below = icmp slt i128 %wide_sum, 0
+  %above = icmp sgt i128 %wide_sum, {max}
+  {clamp}"
+            )
+        }
+    };
+    let product = match overflow {
+        Overflow::Wrap => "%product = mul i64 %value, %factor".to_string(),
+        _ => "%result = call { i64, i1 } @llvm.smul.with.overflow.i64(i64 %value, i64 %factor)
+  %wrapped = extractvalue { i64, i1 } %result, 0
+  %overflowed = extractvalue { i64, i1 } %result, 1
+  %negative = icmp slt i64 %factor, 0
+  %limit = select i1 %negative, i64 -9223372036854775808, i64 9223372036854775807
+  %product = select i1 %overflowed, i64 %limit, i64 %wrapped"
+            .to_string(),
+    };
+    format!(
+        "
+define internal void @add(i64 %i, i64 %v) {{
+  %pointer = {pointer}
+  %value = load {cell}, ptr %pointer
+  {add}
+}}
+
+define internal void @set(i64 %i, i64 %v) {{
+  %pointer = {pointer}
+  store {cell} 0, ptr %pointer
+  call void @add(i64 %i, i64 %v)
+  ret void
+}}
+
+define internal void @mul_add(i64 %dp, i64 %offset, i64 %factor) {{
+  %pointer = {current}
+  %cell = load {cell}, ptr %pointer
+  %zero = icmp eq {cell} %cell, 0
+  br i1 %zero, label %done, label %multiply
+multiply:
+  %i = call i64 @locate(i64 %dp, i64 %offset)
+  %value = {value}
+  {product}
+  call void @add(i64 %i, i64 %product)
+  br label %done
+done:
+  ret void
+}}
+",
+        current = layout.pointer("%dp"),
+        value = cast("%cell", bits, 64, false),
+    )
+}
+
+fn io(layout: &Layout, output: OutputMode, eof: EofPolicy, flush: Flush) -> String {
+    let cell = layout.cell();
+    let pointer = layout.pointer("%i");
+    let value = cast("%value", layout.bits, 32, false);
+    let output = match output {
+        OutputMode::Byte => "call i32 @putchar(i32 %c)
+  ret void"
+            .to_string(),
+        OutputMode::Decimal => {
+            "%format = getelementptr inbounds [4 x i8], ptr @.decimal, i64 0, i64 0
+  call i32 (ptr, ...) @printf(ptr %format, i32 %c)
+  ret void"
+                .to_string()
+        }
+        OutputMode::Utf8 => "%large = icmp ugt i32 %c, 1114111
+  %above_surrogate = icmp uge i32 %c, 55296
+  %below_surrogate = icmp ule i32 %c, 57343
+  %surrogate = and i1 %above_surrogate, %below_surrogate
+  %invalid = or i1 %large, %surrogate
+  %char = select i1 %invalid, i32 65533, i32 %c
+  %one = icmp ult i32 %char, 128
+  br i1 %one, label %one_byte, label %more
+one_byte:
+  call i32 @putchar(i32 %char)
+  ret void
+more:
+  %last = and i32 %char, 63
+  %last_byte = or i32 %last, 128
+  %shift6 = lshr i32 %char, 6
+  %two = icmp ult i32 %char, 2048
+  br i1 %two, label %two_bytes, label %more2
+two_bytes:
+  %lead2 = or i32 %shift6, 192
+  call i32 @putchar(i32 %lead2)
+  call i32 @putchar(i32 %last_byte)
+  ret void
+more2:
+  %middle = and i32 %shift6, 63
+  %middle_byte = or i32 %middle, 128
+  %shift12 = lshr i32 %char, 12
+  %three = icmp ult i32 %char, 65536
+  br i1 %three, label %three_bytes, label %four_bytes
+three_bytes:
+  %lead3 = or i32 %shift12, 224
+  call i32 @putchar(i32 %lead3)
+  call i32 @putchar(i32 %middle_byte)
+  call i32 @putchar(i32 %last_byte)
+  ret void
+four_bytes:
+  %shift18 = lshr i32 %char, 18
+  %lead4 = or i32 %shift18, 240
+  %second = and i32 %shift12, 63
+  %second_byte = or i32 %second, 128
+  call i32 @putchar(i32 %lead4)
+  call i32 @putchar(i32 %second_byte)
+  call i32 @putchar(i32 %middle_byte)
+  call i32 @putchar(i32 %last_byte)
+  ret void"
+            .to_string(),
+    };
+    let eof = match eof {
+        EofPolicy::Zero => format!("store {cell} 0, ptr %pointer\n  "),
+        // -1 truncated to any width is the maximum value of the cell
+        EofPolicy::MinusOne => format!("store {cell} -1, ptr %pointer\n  "),
+        EofPolicy::Unchanged => String::new(),
+    };
+    let flush = match flush {
+        Flush::BeforeInput => "call i32 @fflush(ptr null)\n  ",
+        Flush::AtExit => "",
+    };
+    format!(
+        "
+define internal void @out(i64 %i) {{
+  %pointer = {pointer}
+  %value = load {cell}, ptr %pointer
+  %c = {value}
+  {output}
+}}
+
+define internal void @in(i64 %i) {{
+  %pointer = {pointer}
+  {flush}%c = call i32 @getchar()
+  %eof = icmp eq i32 %c, -1
+  br i1 %eof, label %end_of_file, label %byte
+end_of_file:
+  {eof}ret void
+byte:
+  %value = {byte}
+  store {cell} %value, ptr %pointer
+  ret void
+}}
+",
+        byte = cast("%c", 32, layout.bits, false),
+    )
+}
+
+struct Compiler {
+    emitter: Emitter,
+    layout: Layout,
+    next: usize,
+}
+
+impl Compiler {
+    fn temporary(&mut self) -> String {
+        self.next += 1;
+        format!("%t{}", self.next)
+    }
+
+    fn labels(&mut self) -> usize {
+        self.next += 1;
+        self.next
+    }
+
+    fn label(&mut self, name: &str) {
+        self.emitter.code.push_str(name);
+        self.emitter.code.push_str(":\n");
+    }
+
+    fn dp(&mut self) -> String {
+        let dp = self.temporary();
+        self.emitter.line(&format!("{dp} = load i64, ptr %dp"));
+        dp
+    }
+
+    /// Index of the cell at `offset` from the data pointer.
+    fn index(&mut self, offset: isize) -> String {
+        let dp = self.dp();
+        if offset == 0 {
+            return dp;
+        }
+        let index = self.temporary();
+        self.emitter.line(&format!(
+            "{index} = call i64 @locate(i64 {dp}, i64 {offset})"
+        ));
+        index
+    }
+
+    /// Compares the current cell with zero, returns the `i1` that is true if
+    /// the cell is not zero.
+    fn current_not_zero(&mut self) -> String {
+        let dp = self.dp();
+        let pointer = self.temporary();
+        let value = self.temporary();
+        let not_zero = self.temporary();
+        let cell = self.layout.cell();
+        self.emitter
+            .line(&format!("{pointer} = {}", self.layout.pointer(&dp)));
+        self.emitter
+            .line(&format!("{value} = load {cell}, ptr {pointer}"));
+        self.emitter
+            .line(&format!("{not_zero} = icmp ne {cell} {value}, 0"));
+        not_zero
+    }
+
+    /// Emits the start of a loop that runs while the current cell is not
+    /// zero, returns the number of its labels.
+    fn loop_start(&mut self) -> usize {
+        let n = self.labels();
+        self.emitter.line(&format!("br label %loop{n}"));
+        self.label(&format!("loop{n}"));
+        let not_zero = self.current_not_zero();
+        self.emitter
+            .line(&format!("br i1 {not_zero}, label %body{n}, label %end{n}"));
+        self.label(&format!("body{n}"));
+        n
+    }
+
+    fn loop_end(&mut self, n: usize) {
+        self.emitter.line(&format!("br label %loop{n}"));
+        self.label(&format!("end{n}"));
+    }
+
+    fn tokens(&mut self, tokens: &[Token]) {
+        // The label numbers of the open loops
+        let mut open: Vec<usize> = Vec::new();
+        for visit in walk(tokens) {
+            match visit {
+                Visit::Open(_) => open.push(self.loop_start()),
+                Visit::Close(_) => {
+                    let n = open.pop().unwrap();
+                    self.loop_end(n);
+                }
+                Visit::Token(token) => match token.kind {
+                    TokenKind::Output => self.call_at("out", 0),
+                    TokenKind::Input => self.call_at("in", 0),
+                    TokenKind::OutputAt { offset } => self.call_at("out", offset),
+                    TokenKind::InputAt { offset } => self.call_at("in", offset),
+                    TokenKind::Move(v) => self.move_dp(v),
+                    TokenKind::IncValue(v) => {
+                        let index = self.index(0);
+                        self.emitter
+                            .line(&format!("call void @add(i64 {index}, i64 {v})"));
+                    }
+                    TokenKind::Add { offset, value } => {
+                        let index = self.index(offset);
+                        self.emitter
+                            .line(&format!("call void @add(i64 {index}, i64 {value})"));
+                    }
+                    TokenKind::SetZero => {
+                        let index = self.index(0);
+                        self.emitter
+                            .line(&format!("call void @set(i64 {index}, i64 0)"));
+                    }
+                    TokenKind::Set { offset, value } => {
+                        let index = self.index(offset);
+                        self.emitter
+                            .line(&format!("call void @set(i64 {index}, i64 {value})"));
+                    }
+                    TokenKind::Scan(step) => {
+                        let n = self.loop_start();
+                        self.move_dp(step);
+                        self.loop_end(n);
+                    }
+                    TokenKind::MulAdd { offset, factor } => {
+                        let dp = self.dp();
+                        self.emitter.line(&format!(
+                            "call void @mul_add(i64 {dp}, i64 {offset}, i64 {factor})"
+                        ));
+                    }
+                    // Walked as the `[` and the `]`
+                    TokenKind::Loop(_) => unreachable!(),
+                },
+            }
+        }
+    }
+
+    fn call_at(&mut self, function: &str, offset: isize) {
+        let index = self.index(offset);
+        self.emitter
+            .line(&format!("call void @{function}(i64 {index})"));
+    }
+
+    fn move_dp(&mut self, v: isize) {
+        let index = self.index(v);
+        self.emitter.line(&format!("store i64 {index}, ptr %dp"));
+    }
+}
+
+/// Translates the tokens to a textual LLVM IR module with the same tape,
+/// cell and I/O behavior as an [`Interpreter`](crate::Interpreter) with
+/// `options`. The tape is a global array, so it must have a fixed size.
+/// Pointers are opaque `ptr`s, the default since LLVM 15.
+pub fn emit_llvm(program: &[Token], options: &Options) -> Result<String, Error> {
+    let len = match options.tape {
+        Tape::Fixed(len) => len,
+        _ => {
+            return Err(Error::Unsupported(
+                "only fixed size tapes can be compiled to LLVM IR",
+            ))
+        }
+    };
+    let bits = match options.cell {
+        CellWidth::U8 => 8,
+        CellWidth::U16 => 16,
+        CellWidth::U32 => 32,
+    };
+    let layout = Layout { bits, len };
+
+    let mut emitter = Emitter::default();
+    emitter.line("; Generated by mindfuck");
+    emitter.line(&format!(
+        "@tape = internal global [{len} x {}] zeroinitializer",
+        layout.cell()
+    ));
+    emitter.code.push_str(PRELUDE);
+    emitter.code.push_str(&locate(&layout, options.edge));
+    emitter
+        .code
+        .push_str(&arithmetic(&layout, options.overflow));
+    emitter
+        .code
+        .push_str(&io(&layout, options.output, options.eof, options.flush));
+    emitter.code.push_str("\ndefine i32 @main() {\nentry:\n");
+
+    let mut compiler = Compiler {
+        emitter,
+        layout,
+        next: 0,
+    };
+    compiler.emitter.indent = 1;
+    compiler.emitter.line("%dp = alloca i64");
+    compiler.emitter.line("store i64 0, ptr %dp");
+    compiler.tokens(program);
+    compiler.emitter.line("call i32 @fflush(ptr null)");
+    compiler.emitter.line("ret i32 0");
+    compiler.emitter.code.push_str("}\n");
+    Ok(compiler.emitter.code)
+}
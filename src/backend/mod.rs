@@ -0,0 +1,28 @@
+//! Code generators that translate optimized tokens to other languages.
+
+pub mod c;
+pub mod elf;
+pub mod llvm;
+pub mod wasm;
+pub(crate) mod x86_64;
+
+/// Deepest indentation level written, deeper lines are indented like the ones
+/// at this level so that the code of deeply nested loops stays linear in size.
+const MAX_INDENT: usize = 16;
+
+/// Accumulates generated source code line by line.
+#[derive(Default)]
+struct Emitter {
+    code: String,
+    indent: usize,
+}
+
+impl Emitter {
+    fn line(&mut self, line: &str) {
+        for _ in 0..self.indent.min(MAX_INDENT) {
+            self.code.push_str("    ");
+        }
+        self.code.push_str(line);
+        self.code.push('\n');
+    }
+}
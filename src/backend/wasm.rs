@@ -0,0 +1,581 @@
+use crate::{
+    config::{CellWidth, EdgePolicy, EofPolicy, Options, Overflow, Tape},
+    error::Error,
+    token::{walk, Token, TokenKind, Visit},
+};
+
+use super::Emitter;
+
+/// Offsets and tape sizes must stay below this so `dp + offset` fits in an i32.
+const LIMIT: isize = 1 << 30;
+const PAGE: usize = 65536;
+
+/// The subset of WebAssembly instructions used by the generated module.
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Unreachable,
+    Block,
+    Loop,
+    If,
+    Else,
+    End,
+    Br(u32),
+    BrIf(u32),
+    Return,
+    Call(u32),
+    Select,
+    LocalGet(u32),
+    LocalSet(u32),
+    LocalTee(u32),
+    /// Loads the cell at the address on the stack.
+    Load,
+    /// Stores a value in the cell at the address below it.
+    Store,
+    Const(i32),
+    Eqz,
+    Eq,
+    LtS,
+    LtU,
+    Add,
+    Mul,
+    RemS,
+    Shl,
+}
+
+// Function indices, the imports come first
+const OUTPUT: u32 = 0;
+const INPUT: u32 = 1;
+const LOCATE: u32 = 2;
+const READ: u32 = 3;
+const RUN: u32 = 4;
+
+/// Types of the functions: `(i32)`, `() -> i32`, `(i32 i32) -> i32` and `()`.
+const TYPES: [(&[u8], &[u8]); 4] = [
+    (&[0x7f], &[]),
+    (&[], &[0x7f]),
+    (&[0x7f, 0x7f], &[0x7f]),
+    (&[], &[]),
+];
+
+struct Function {
+    name: &'static str,
+    ty: u32,
+    params: &'static [&'static str],
+    locals: &'static [&'static str],
+    body: Vec<Op>,
+}
+
+struct Module {
+    width: CellWidth,
+    pages: usize,
+    functions: Vec<Function>,
+}
+
+/// Pushes the byte address of the cell whose index is on the stack.
+fn address(body: &mut Vec<Op>, width: CellWidth) {
+    match width {
+        CellWidth::U8 => {}
+        CellWidth::U16 => body.extend([Op::Const(1), Op::Shl]),
+        CellWidth::U32 => body.extend([Op::Const(2), Op::Shl]),
+    }
+}
+
+/// `locate(dp, offset)`: the index of the cell `offset` cells away from `dp`.
+fn locate(len: i32, edge: EdgePolicy) -> Function {
+    let mut body = vec![
+        Op::LocalGet(0),
+        Op::LocalGet(1),
+        Op::Add,
+        Op::LocalTee(0),
+        Op::Const(len),
+        Op::LtU,
+        Op::If,
+        Op::LocalGet(0),
+        Op::Return,
+        Op::End,
+    ];
+    match edge {
+        EdgePolicy::Wrap => body.extend([
+            Op::LocalGet(0),
+            Op::Const(len),
+            Op::RemS,
+            Op::LocalTee(0),
+            Op::Const(0),
+            Op::LtS,
+            Op::If,
+            Op::LocalGet(0),
+            Op::Const(len),
+            Op::Add,
+            Op::Return,
+            Op::End,
+            Op::LocalGet(0),
+        ]),
+        EdgePolicy::Clamp => body.extend([
+            Op::Const(0),
+            Op::Const(len - 1),
+            Op::LocalGet(0),
+            Op::Const(0),
+            Op::LtS,
+            Op::Select,
+        ]),
+        EdgePolicy::Error => body.push(Op::Unreachable),
+    }
+    Function {
+        name: "locate",
+        ty: 2,
+        params: &["index", "offset"],
+        locals: &[],
+        body,
+    }
+}
+
+/// `read(index)`: reads a byte from the host into the cell at `index`.
+fn read(width: CellWidth, eof: EofPolicy) -> Function {
+    let mut body = vec![Op::Call(INPUT), Op::LocalSet(1)];
+    if width != CellWidth::U8 {
+        body.push(Op::LocalGet(0));
+        address(&mut body, width);
+        body.push(Op::LocalSet(0));
+    }
+    body.extend([Op::LocalGet(1), Op::Const(-1), Op::Eq, Op::If]);
+    match eof {
+        // -1 stored in a cell of any width is its maximum value
+        EofPolicy::Zero => body.extend([Op::LocalGet(0), Op::Const(0), Op::Store]),
+        EofPolicy::MinusOne => body.extend([Op::LocalGet(0), Op::Const(-1), Op::Store]),
+        EofPolicy::Unchanged => {}
+    }
+    body.extend([
+        Op::Else,
+        Op::LocalGet(0),
+        Op::LocalGet(1),
+        Op::Store,
+        Op::End,
+    ]);
+    Function {
+        name: "read",
+        ty: 0,
+        params: &["index"],
+        locals: &["byte"],
+        body,
+    }
+}
+
+// Locals of `run`
+const DP: u32 = 0;
+const CELL: u32 = 1;
+
+struct Compiler {
+    width: CellWidth,
+    body: Vec<Op>,
+}
+
+impl Compiler {
+    /// Pushes the index of the cell at `offset` from the data pointer.
+    fn index(&mut self, offset: isize) -> Result<(), Error> {
+        self.body.push(Op::LocalGet(DP));
+        if offset != 0 {
+            if offset.abs() >= LIMIT {
+                return Err(Error::Unsupported("offsets must be smaller than 2^30"));
+            }
+            self.body
+                .extend([Op::Const(offset as i32), Op::Call(LOCATE)]);
+        }
+        Ok(())
+    }
+
+    /// Pushes the byte address of the cell at `offset` from the data pointer.
+    fn cell(&mut self, offset: isize) -> Result<(), Error> {
+        self.index(offset)?;
+        address(&mut self.body, self.width);
+        Ok(())
+    }
+
+    /// Adds the value pushed by `value` to the cell at `offset`, wrapping.
+    fn add(&mut self, offset: isize, value: &[Op]) -> Result<(), Error> {
+        self.cell(offset)?;
+        self.body
+            .extend([Op::LocalTee(CELL), Op::LocalGet(CELL), Op::Load]);
+        self.body.extend_from_slice(value);
+        self.body.extend([Op::Add, Op::Store]);
+        Ok(())
+    }
+
+    fn load_current(&mut self) {
+        self.body.push(Op::LocalGet(DP));
+        address(&mut self.body, self.width);
+        self.body.push(Op::Load);
+    }
+
+    fn tokens(&mut self, tokens: &[Token]) -> Result<(), Error> {
+        for visit in walk(tokens) {
+            match visit {
+                Visit::Open(_) => {
+                    self.body.push(Op::Block);
+                    self.load_current();
+                    self.body.extend([Op::Eqz, Op::BrIf(0), Op::Loop]);
+                }
+                Visit::Close(_) => {
+                    self.load_current();
+                    self.body.extend([Op::BrIf(0), Op::End, Op::End]);
+                }
+                Visit::Token(token) => match token.kind {
+                    TokenKind::Output => self.output(0)?,
+                    TokenKind::Input => self.input(0)?,
+                    TokenKind::Move(v) => {
+                        self.index(v)?;
+                        self.body.push(Op::LocalSet(DP));
+                    }
+                    // Values are truncated to 32 bits, which doesn't change a wrapping sum
+                    TokenKind::IncValue(v) => self.add(0, &[Op::Const(v as i32)])?,
+                    TokenKind::Add { offset, value } => {
+                        self.add(offset, &[Op::Const(value as i32)])?
+                    }
+                    TokenKind::SetZero => self.set(0, 0)?,
+                    TokenKind::Set { offset, value } => self.set(offset, value)?,
+                    TokenKind::OutputAt { offset } => self.output(offset)?,
+                    TokenKind::InputAt { offset } => self.input(offset)?,
+                    TokenKind::Scan(step) => {
+                        self.body.extend([Op::Block, Op::Loop]);
+                        self.load_current();
+                        self.body.extend([Op::Eqz, Op::BrIf(1)]);
+                        self.index(step)?;
+                        self.body
+                            .extend([Op::LocalSet(DP), Op::Br(0), Op::End, Op::End]);
+                    }
+                    TokenKind::MulAdd { offset, factor } => {
+                        self.load_current();
+                        self.body.push(Op::If);
+                        let mut value = vec![Op::LocalGet(DP)];
+                        address(&mut value, self.width);
+                        value.extend([Op::Load, Op::Const(factor as i32), Op::Mul]);
+                        self.add(offset, &value)?;
+                        self.body.push(Op::End);
+                    }
+                    // Walked as the `[` and the `]`
+                    TokenKind::Loop(_) => unreachable!(),
+                },
+            }
+        }
+        Ok(())
+    }
+
+    fn set(&mut self, offset: isize, value: isize) -> Result<(), Error> {
+        self.cell(offset)?;
+        self.body.extend([Op::Const(value as i32), Op::Store]);
+        Ok(())
+    }
+
+    fn output(&mut self, offset: isize) -> Result<(), Error> {
+        self.cell(offset)?;
+        self.body.extend([Op::Load, Op::Call(OUTPUT)]);
+        Ok(())
+    }
+
+    fn input(&mut self, offset: isize) -> Result<(), Error> {
+        self.index(offset)?;
+        self.body.push(Op::Call(READ));
+        Ok(())
+    }
+}
+
+fn compile(program: &[Token], options: &Options) -> Result<Module, Error> {
+    let len = match options.tape {
+        Tape::Fixed(len) if (len as isize) < LIMIT => len,
+        Tape::Fixed(_) => return Err(Error::Unsupported("tapes must be smaller than 2^30 cells")),
+        _ => {
+            return Err(Error::Unsupported(
+                "only fixed size tapes can be compiled to wasm",
+            ))
+        }
+    };
+    if options.overflow != Overflow::Wrap {
+        return Err(Error::Unsupported(
+            "only wrapping cells can be compiled to wasm",
+        ));
+    }
+    let bytes = len
+        * match options.cell {
+            CellWidth::U8 => 1,
+            CellWidth::U16 => 2,
+            CellWidth::U32 => 4,
+        };
+
+    let mut compiler = Compiler {
+        width: options.cell,
+        body: Vec::new(),
+    };
+    compiler.tokens(program)?;
+    Ok(Module {
+        width: options.cell,
+        pages: bytes.div_ceil(PAGE),
+        functions: vec![
+            locate(len as i32, options.edge),
+            read(options.cell, options.eof),
+            Function {
+                name: "run",
+                ty: 3,
+                params: &[],
+                locals: &["dp", "cell"],
+                body: compiler.body,
+            },
+        ],
+    })
+}
+
+fn unsigned(bytes: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+fn signed(bytes: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+fn name(bytes: &mut Vec<u8>, name: &str) {
+    unsigned(bytes, name.len() as u64);
+    bytes.extend_from_slice(name.as_bytes());
+}
+
+fn section(wasm: &mut Vec<u8>, id: u8, count: usize, content: &[u8]) {
+    let mut body = Vec::new();
+    unsigned(&mut body, count as u64);
+    body.extend_from_slice(content);
+    wasm.push(id);
+    unsigned(wasm, body.len() as u64);
+    wasm.extend_from_slice(&body);
+}
+
+impl Module {
+    fn memory_op(&self, op: Op) -> (u8, &'static str, u8) {
+        match (op, self.width) {
+            (Op::Load, CellWidth::U8) => (0x2d, "i32.load8_u", 0),
+            (Op::Load, CellWidth::U16) => (0x2f, "i32.load16_u", 1),
+            (Op::Load, CellWidth::U32) => (0x28, "i32.load", 2),
+            (Op::Store, CellWidth::U8) => (0x3a, "i32.store8", 0),
+            (Op::Store, CellWidth::U16) => (0x3b, "i32.store16", 1),
+            (_, CellWidth::U32) => (0x36, "i32.store", 2),
+            _ => unreachable!(),
+        }
+    }
+
+    fn encode(&self, code: &mut Vec<u8>, op: Op) {
+        match op {
+            Op::Unreachable => code.push(0x00),
+            Op::Block => code.extend([0x02, 0x40]),
+            Op::Loop => code.extend([0x03, 0x40]),
+            Op::If => code.extend([0x04, 0x40]),
+            Op::Else => code.push(0x05),
+            Op::End => code.push(0x0b),
+            Op::Br(depth) => {
+                code.push(0x0c);
+                unsigned(code, depth as u64);
+            }
+            Op::BrIf(depth) => {
+                code.push(0x0d);
+                unsigned(code, depth as u64);
+            }
+            Op::Return => code.push(0x0f),
+            Op::Call(f) => {
+                code.push(0x10);
+                unsigned(code, f as u64);
+            }
+            Op::Select => code.push(0x1b),
+            Op::LocalGet(i) => {
+                code.push(0x20);
+                unsigned(code, i as u64);
+            }
+            Op::LocalSet(i) => {
+                code.push(0x21);
+                unsigned(code, i as u64);
+            }
+            Op::LocalTee(i) => {
+                code.push(0x22);
+                unsigned(code, i as u64);
+            }
+            Op::Load | Op::Store => {
+                let (opcode, _, align) = self.memory_op(op);
+                code.extend([opcode, align, 0]);
+            }
+            Op::Const(v) => {
+                code.push(0x41);
+                signed(code, v as i64);
+            }
+            Op::Eqz => code.push(0x45),
+            Op::Eq => code.push(0x46),
+            Op::LtS => code.push(0x48),
+            Op::LtU => code.push(0x49),
+            Op::Add => code.push(0x6a),
+            Op::Mul => code.push(0x6c),
+            Op::RemS => code.push(0x6f),
+            Op::Shl => code.push(0x74),
+        }
+    }
+
+    fn binary(&self) -> Vec<u8> {
+        let mut wasm = b"\0asm".to_vec();
+        wasm.extend_from_slice(&1u32.to_le_bytes());
+
+        let mut types = Vec::new();
+        for (params, results) in TYPES {
+            types.push(0x60);
+            unsigned(&mut types, params.len() as u64);
+            types.extend_from_slice(params);
+            unsigned(&mut types, results.len() as u64);
+            types.extend_from_slice(results);
+        }
+        section(&mut wasm, 1, TYPES.len(), &types);
+
+        let mut imports = Vec::new();
+        for (field, ty) in [("output", 0), ("input", 1)] {
+            name(&mut imports, "env");
+            name(&mut imports, field);
+            imports.extend([0x00, ty]);
+        }
+        section(&mut wasm, 2, 2, &imports);
+
+        let functions: Vec<u8> = self.functions.iter().map(|f| f.ty as u8).collect();
+        section(&mut wasm, 3, functions.len(), &functions);
+
+        // The memory can't grow, as the tape has a fixed size
+        let mut memory = vec![0x01];
+        unsigned(&mut memory, self.pages as u64);
+        unsigned(&mut memory, self.pages as u64);
+        section(&mut wasm, 5, 1, &memory);
+
+        let mut exports = Vec::new();
+        name(&mut exports, "memory");
+        exports.extend([0x02, 0x00]);
+        name(&mut exports, "run");
+        exports.push(0x00);
+        unsigned(&mut exports, RUN as u64);
+        section(&mut wasm, 7, 2, &exports);
+
+        let mut code = Vec::new();
+        for function in &self.functions {
+            let mut body = Vec::new();
+            if function.locals.is_empty() {
+                body.push(0);
+            } else {
+                body.push(1);
+                unsigned(&mut body, function.locals.len() as u64);
+                body.push(0x7f);
+            }
+            for &op in &function.body {
+                self.encode(&mut body, op);
+            }
+            body.push(0x0b);
+            unsigned(&mut code, body.len() as u64);
+            code.extend_from_slice(&body);
+        }
+        section(&mut wasm, 10, self.functions.len(), &code);
+        wasm
+    }
+
+    fn text(&self) -> String {
+        let function_names = ["$output", "$input", "$locate", "$read", "$run"];
+        let mut emitter = Emitter::default();
+        emitter.line(";; Generated by mindfuck");
+        emitter.line("(module");
+        emitter.indent = 1;
+        emitter.line("(import \"env\" \"output\" (func $output (param i32)))");
+        emitter.line("(import \"env\" \"input\" (func $input (result i32)))");
+        emitter.line(&format!(
+            "(memory (export \"memory\") {} {})",
+            self.pages, self.pages
+        ));
+        for function in &self.functions {
+            let mut signature = format!("(func ${}", function.name);
+            if function.name == "run" {
+                signature.push_str(" (export \"run\")");
+            }
+            for param in function.params {
+                signature.push_str(&format!(" (param ${param} i32)"));
+            }
+            if TYPES[function.ty as usize].1.len() == 1 {
+                signature.push_str(" (result i32)");
+            }
+            emitter.line(&signature);
+            emitter.indent += 1;
+            for local in function.locals {
+                emitter.line(&format!("(local ${local} i32)"));
+            }
+            let names: Vec<&str> = function
+                .params
+                .iter()
+                .chain(function.locals)
+                .copied()
+                .collect();
+            for &op in &function.body {
+                let line = match op {
+                    Op::Unreachable => "unreachable".to_string(),
+                    Op::Block => "block".to_string(),
+                    Op::Loop => "loop".to_string(),
+                    Op::If => "if".to_string(),
+                    Op::Else => "else".to_string(),
+                    Op::End => "end".to_string(),
+                    Op::Br(depth) => format!("br {depth}"),
+                    Op::BrIf(depth) => format!("br_if {depth}"),
+                    Op::Return => "return".to_string(),
+                    Op::Call(f) => format!("call {}", function_names[f as usize]),
+                    Op::Select => "select".to_string(),
+                    Op::LocalGet(i) => format!("local.get ${}", names[i as usize]),
+                    Op::LocalSet(i) => format!("local.set ${}", names[i as usize]),
+                    Op::LocalTee(i) => format!("local.tee ${}", names[i as usize]),
+                    Op::Load | Op::Store => self.memory_op(op).1.to_string(),
+                    Op::Const(v) => format!("i32.const {v}"),
+                    Op::Eqz => "i32.eqz".to_string(),
+                    Op::Eq => "i32.eq".to_string(),
+                    Op::LtS => "i32.lt_s".to_string(),
+                    Op::LtU => "i32.lt_u".to_string(),
+                    Op::Add => "i32.add".to_string(),
+                    Op::Mul => "i32.mul".to_string(),
+                    Op::RemS => "i32.rem_s".to_string(),
+                    Op::Shl => "i32.shl".to_string(),
+                };
+                if matches!(op, Op::Else | Op::End) {
+                    emitter.indent -= 1;
+                }
+                emitter.line(&line);
+                if matches!(op, Op::Block | Op::Loop | Op::If | Op::Else) {
+                    emitter.indent += 1;
+                }
+            }
+            emitter.indent -= 1;
+            emitter.line(")");
+        }
+        emitter.indent = 0;
+        emitter.line(")");
+        emitter.code
+    }
+}
+
+/// Compiles the tokens to a binary WebAssembly module.
+///
+/// The module imports `env.output(value: i32)`, which receives the value of
+/// a cell, and `env.input() -> i32`, which returns the next byte or -1 on
+/// EOF. It exports its linear memory, which holds the tape, and `run`.
+/// Only wrapping cells on a fixed tape are supported, and with the `error`
+/// edge policy leaving the tape traps.
+pub fn emit_wasm(program: &[Token], options: &Options) -> Result<Vec<u8>, Error> {
+    Ok(compile(program, options)?.binary())
+}
+
+/// Same as [`emit_wasm`], in the WebAssembly text format.
+pub fn emit_wat(program: &[Token], options: &Options) -> Result<String, Error> {
+    Ok(compile(program, options)?.text())
+}
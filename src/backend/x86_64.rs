@@ -0,0 +1,209 @@
+use crate::token::{walk, Span, Token, TokenKind, Visit};
+
+/// Where a runtime routine called by the generated code lives.
+pub(crate) enum Routine {
+    /// A function at an absolute address.
+    #[cfg_attr(not(feature = "jit"), allow(dead_code))]
+    Absolute(usize),
+    /// Code at this position of the generated code.
+    Local(usize),
+}
+
+/// Routines called by the generated code with the cell address in rsi.
+///
+/// `output` and `input` return 0 in al on success, `locate` receives an
+/// address outside the tape and returns in rax the address to use instead,
+/// null on error. All of them receive in edx the index in
+/// [`Assembler::spans`] of the span of the calling token and in rcx the data
+/// pointer.
+pub(crate) struct Routines {
+    pub(crate) output: Routine,
+    pub(crate) input: Routine,
+    pub(crate) locate: Routine,
+}
+
+/// Machine code generator for 8 bit wrapping cells, the data pointer lives in
+/// rbx, the tape bounds in r12 and r13. Before each routine call r14 is
+/// copied to rdi.
+pub(crate) struct Assembler {
+    pub(crate) code: Vec<u8>,
+    /// Positions of the rel32 of the jumps to the error exit.
+    errors: Vec<usize>,
+    pub(crate) routines: Routines,
+    /// Span of the token of every routine call, in the order of the calls.
+    pub(crate) spans: Vec<Span>,
+    /// Span of the token being emitted.
+    span: Span,
+}
+
+impl Assembler {
+    pub(crate) fn new(routines: Routines) -> Self {
+        Self {
+            code: Vec::new(),
+            errors: Vec::new(),
+            routines,
+            spans: Vec::new(),
+            span: Span::default(),
+        }
+    }
+    pub(crate) fn emit(&mut self, bytes: &[u8]) {
+        self.code.extend_from_slice(bytes)
+    }
+    /// Emits a jump with a rel32 placeholder, returns the position of the rel32.
+    pub(crate) fn jump(&mut self, opcode: &[u8]) -> usize {
+        self.emit(opcode);
+        self.emit(&[0; 4]);
+        self.code.len() - 4
+    }
+    pub(crate) fn patch(&mut self, at: usize, target: usize) {
+        let rel = target as i32 - (at + 4) as i32;
+        self.code[at..at + 4].copy_from_slice(&rel.to_le_bytes())
+    }
+    pub(crate) fn jump_to(&mut self, opcode: &[u8], target: usize) {
+        let at = self.jump(opcode);
+        self.patch(at, target)
+    }
+    /// Points all the jumps to the error exit at `target`.
+    pub(crate) fn patch_errors(&mut self, target: usize) {
+        for at in std::mem::take(&mut self.errors) {
+            self.patch(at, target);
+        }
+    }
+    fn call(&mut self, routine: fn(&Routines) -> &Routine) {
+        self.emit(&[0x4c, 0x89, 0xf7]); // mov rdi, r14
+        self.emit(&[0xba]); // mov edx, imm32
+        self.emit(&(self.spans.len() as u32).to_le_bytes());
+        self.spans.push(self.span);
+        self.emit(&[0x48, 0x89, 0xd9]); // mov rcx, rbx
+        match *routine(&self.routines) {
+            Routine::Absolute(function) => {
+                self.emit(&[0x48, 0xb8]); // mov rax, imm64
+                self.emit(&(function as u64).to_le_bytes());
+                self.emit(&[0xff, 0xd0]); // call rax
+            }
+            Routine::Local(target) => self.jump_to(&[0xe8], target), // call rel32
+        }
+    }
+    /// Leaves in rax the address of the cell at `offset` from the data pointer.
+    fn address(&mut self, offset: i32) {
+        self.emit(&[0x48, 0x8d, 0x83]); // lea rax, [rbx + disp32]
+        self.emit(&offset.to_le_bytes());
+        self.emit(&[0x4c, 0x39, 0xe0]); // cmp rax, r12
+        let below = self.jump(&[0x0f, 0x82]); // jb
+        self.emit(&[0x4c, 0x39, 0xe8]); // cmp rax, r13
+        let inside = self.jump(&[0x0f, 0x82]); // jb
+        let outside = self.code.len();
+        self.patch(below, outside);
+        self.emit(&[0x48, 0x89, 0xc6]); // mov rsi, rax
+        self.call(|routines| &routines.locate);
+        self.emit(&[0x48, 0x85, 0xc0]); // test rax, rax
+        let error = self.jump(&[0x0f, 0x84]); // jz
+        self.errors.push(error);
+        let len = self.code.len();
+        self.patch(inside, len);
+    }
+    /// Emits an instruction on the cell at `offset`, addressed by rbx if
+    /// the offset is zero and by rax otherwise.
+    fn on_cell(&mut self, offset: i32, rax: &[u8], rbx: &[u8]) {
+        if offset == 0 {
+            self.emit(rbx)
+        } else {
+            self.address(offset);
+            self.emit(rax)
+        }
+    }
+    fn io(&mut self, offset: i32, routine: fn(&Routines) -> &Routine) {
+        self.on_cell(
+            offset,
+            &[0x48, 0x89, 0xc6], // mov rsi, rax
+            &[0x48, 0x89, 0xde], // mov rsi, rbx
+        );
+        self.call(routine);
+        self.emit(&[0x84, 0xc0]); // test al, al
+        let error = self.jump(&[0x0f, 0x85]); // jnz
+        self.errors.push(error);
+    }
+    fn move_dp(&mut self, v: i32) {
+        self.address(v);
+        self.emit(&[0x48, 0x89, 0xc3]); // mov rbx, rax
+    }
+    fn loop_start(&mut self) -> usize {
+        self.emit(&[0x80, 0x3b, 0x00]); // cmp byte [rbx], 0
+        self.jump(&[0x0f, 0x84]) // je
+    }
+
+    /// Emits the code of the tokens, `None` if an offset does not fit in 32 bits.
+    pub(crate) fn tokens(&mut self, tokens: &[Token]) -> Option<()> {
+        // For every open loop, the jump to patch with its end and the start of its body
+        let mut open: Vec<(usize, usize)> = Vec::new();
+        for visit in walk(tokens) {
+            match visit {
+                Visit::Open(_) => {
+                    let end = self.loop_start();
+                    open.push((end, self.code.len()));
+                }
+                Visit::Close(_) => {
+                    let (end, start) = open.pop().unwrap();
+                    self.emit(&[0x80, 0x3b, 0x00]); // cmp byte [rbx], 0
+                    self.jump_to(&[0x0f, 0x85], start); // jne
+                    let len = self.code.len();
+                    self.patch(end, len);
+                }
+                Visit::Token(token) => {
+                    self.span = token.span;
+                    self.token(token)?
+                }
+            }
+        }
+        Some(())
+    }
+
+    /// Emits the code of a token, loops excluded.
+    fn token(&mut self, token: &Token) -> Option<()> {
+        match &token.kind {
+            TokenKind::Output => self.io(0, |routines| &routines.output),
+            TokenKind::Input => self.io(0, |routines| &routines.input),
+            TokenKind::OutputAt { offset } => {
+                self.io(i32::try_from(*offset).ok()?, |routines| &routines.output)
+            }
+            TokenKind::InputAt { offset } => {
+                self.io(i32::try_from(*offset).ok()?, |routines| &routines.input)
+            }
+            TokenKind::Move(v) => self.move_dp(i32::try_from(*v).ok()?),
+            TokenKind::IncValue(v) => self.emit(&[0x80, 0x03, *v as u8]), // add byte [rbx], imm8
+            TokenKind::SetZero => self.emit(&[0xc6, 0x03, 0x00]),         // mov byte [rbx], 0
+            TokenKind::Add { offset, value } => self.on_cell(
+                i32::try_from(*offset).ok()?,
+                &[0x80, 0x00, *value as u8], // add byte [rax], imm8
+                &[0x80, 0x03, *value as u8], // add byte [rbx], imm8
+            ),
+            TokenKind::Set { offset, value } => self.on_cell(
+                i32::try_from(*offset).ok()?,
+                &[0xc6, 0x00, *value as u8], // mov byte [rax], imm8
+                &[0xc6, 0x03, *value as u8], // mov byte [rbx], imm8
+            ),
+            TokenKind::MulAdd { offset, factor } => {
+                self.emit(&[0x80, 0x3b, 0x00]); // cmp byte [rbx], 0
+                let skip = self.jump(&[0x0f, 0x84]); // je
+                self.address(i32::try_from(*offset).ok()?);
+                self.emit(&[0x0f, 0xb6, 0x0b]); // movzx ecx, byte [rbx]
+                self.emit(&[0x69, 0xc9]); // imul ecx, ecx, imm32
+                self.emit(&(*factor as i32).to_le_bytes());
+                self.emit(&[0x00, 0x08]); // add byte [rax], cl
+                let len = self.code.len();
+                self.patch(skip, len);
+            }
+            TokenKind::Scan(step) => {
+                let start = self.code.len();
+                let end = self.loop_start();
+                self.move_dp(i32::try_from(*step).ok()?);
+                self.jump_to(&[0xe9], start); // jmp
+                let len = self.code.len();
+                self.patch(end, len);
+            }
+            // Walked as the `[` and the `]`
+            TokenKind::Loop(_) => unreachable!(),
+        }
+        Some(())
+    }
+}
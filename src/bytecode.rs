@@ -0,0 +1,102 @@
+use crate::token::{walk, Span, Token, TokenKind, Visit};
+
+/// A flat instruction, loops are lowered to jumps with resolved targets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    Output,
+    Input,
+    Move(isize),
+    IncValue(isize),
+    SetZero,
+    Scan(isize),
+    MulAdd {
+        offset: isize,
+        factor: isize,
+    },
+    Add {
+        offset: isize,
+        value: isize,
+    },
+    Set {
+        offset: isize,
+        value: isize,
+    },
+    OutputAt {
+        offset: isize,
+    },
+    InputAt {
+        offset: isize,
+    },
+    /// Start of a loop, jumps past the matching `JumpIfNotZero` if the cell is zero.
+    JumpIfZero(usize),
+    /// End of a loop, jumps past the matching `JumpIfZero` if the cell is not zero.
+    JumpIfNotZero(usize),
+}
+
+/// Bytecode with the source span of each instruction.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Program {
+    pub instructions: Vec<Instruction>,
+    /// `spans[pc]` is the span of `instructions[pc]`.
+    pub spans: Vec<Span>,
+}
+
+impl Program {
+    fn push(&mut self, instruction: Instruction, span: Span) {
+        self.instructions.push(instruction);
+        self.spans.push(span);
+    }
+}
+
+fn lower_token(kind: &TokenKind, span: Span, program: &mut Program) {
+    match kind {
+        TokenKind::Output => program.push(Instruction::Output, span),
+        TokenKind::Input => program.push(Instruction::Input, span),
+        TokenKind::Move(v) => program.push(Instruction::Move(*v), span),
+        TokenKind::IncValue(v) => program.push(Instruction::IncValue(*v), span),
+        TokenKind::SetZero => program.push(Instruction::SetZero, span),
+        TokenKind::Scan(step) => program.push(Instruction::Scan(*step), span),
+        &TokenKind::MulAdd { offset, factor } => {
+            program.push(Instruction::MulAdd { offset, factor }, span)
+        }
+        &TokenKind::Add { offset, value } => program.push(Instruction::Add { offset, value }, span),
+        &TokenKind::Set { offset, value } => program.push(Instruction::Set { offset, value }, span),
+        &TokenKind::OutputAt { offset } => program.push(Instruction::OutputAt { offset }, span),
+        &TokenKind::InputAt { offset } => program.push(Instruction::InputAt { offset }, span),
+        // Walked as the `[` and the `]`
+        TokenKind::Loop(_) => unreachable!(),
+    }
+}
+
+/// Lowers a token tree into flat bytecode.
+pub fn lower(tokens: &[Token]) -> Program {
+    let mut program = Program {
+        instructions: Vec::with_capacity(tokens.len()),
+        spans: Vec::with_capacity(tokens.len()),
+    };
+    // The pcs of the `JumpIfZero` of the open loops
+    let mut open: Vec<usize> = Vec::new();
+    for visit in walk(tokens) {
+        match visit {
+            Visit::Token(Token { kind, span }) => lower_token(kind, *span, &mut program),
+            // The jumps get the spans of the brackets
+            Visit::Open(Token { span, .. }) => {
+                open.push(program.instructions.len());
+                program.push(
+                    Instruction::JumpIfZero(0),
+                    Span::new(span.start, span.start + 1),
+                );
+            }
+            Visit::Close(Token { span, .. }) => {
+                let start = open.pop().unwrap();
+                let end = program.instructions.len();
+                program.push(
+                    Instruction::JumpIfNotZero(start + 1),
+                    Span::new(span.end.saturating_sub(1), span.end),
+                );
+                program.instructions[start] = Instruction::JumpIfZero(end + 1);
+            }
+        }
+    }
+    program
+}
@@ -0,0 +1,50 @@
+use std::fmt::Debug;
+
+use crate::config::{CellWidth, Overflow};
+
+/// An unsigned integer type usable as a tape cell.
+pub trait Cell: Copy + Default + PartialEq + Debug + 'static {
+    const WIDTH: CellWidth;
+    const MAX: Self;
+
+    /// Adds `v` to the cell, returns `None` if the result traps.
+    fn add(self, v: isize, overflow: Overflow) -> Option<Self>;
+    fn from_u8(byte: u8) -> Self;
+    /// The value as a cell, `None` if it does not fit.
+    fn from_u32(value: u32) -> Option<Self>;
+    fn to_u32(self) -> u32;
+}
+
+macro_rules! impl_cell {
+    ($t:ty, $width:expr) => {
+        impl Cell for $t {
+            const WIDTH: CellWidth = $width;
+            const MAX: Self = <$t>::MAX;
+
+            fn add(self, v: isize, overflow: Overflow) -> Option<Self> {
+                if overflow == Overflow::Wrap {
+                    // Truncating `v` keeps it congruent modulo the cell size
+                    return Some(self.wrapping_add(v as $t));
+                }
+                let sum = self as i128 + v as i128;
+                match overflow {
+                    Overflow::Saturate => Some(sum.clamp(0, <$t>::MAX as i128) as $t),
+                    _ => <$t>::try_from(sum).ok(),
+                }
+            }
+            fn from_u8(byte: u8) -> Self {
+                byte as $t
+            }
+            fn from_u32(value: u32) -> Option<Self> {
+                <$t>::try_from(value).ok()
+            }
+            fn to_u32(self) -> u32 {
+                self as u32
+            }
+        }
+    };
+}
+
+impl_cell!(u8, CellWidth::U8);
+impl_cell!(u16, CellWidth::U16);
+impl_cell!(u32, CellWidth::U32);
@@ -0,0 +1,237 @@
+use std::{str::FromStr, time::Duration};
+
+use crate::interpreter::MEMORY_SIZE;
+
+/// Shape of the tape the interpreter runs on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tape {
+    /// A tape of exactly N cells, [`Interpreter::new`](crate::Interpreter::new)
+    /// turns a size of 0 into 1.
+    Fixed(usize),
+    /// Starts at cell 0 and grows to the right on demand.
+    Unbounded,
+    /// Grows on demand in both directions.
+    Bidirectional,
+}
+
+impl Default for Tape {
+    fn default() -> Self {
+        Tape::Fixed(MEMORY_SIZE)
+    }
+}
+
+impl FromStr for Tape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unbounded" => Ok(Tape::Unbounded),
+            "bidirectional" => Ok(Tape::Bidirectional),
+            _ => match s.parse::<usize>() {
+                Ok(0) => Err("the tape must have at least one cell".to_string()),
+                Ok(n) => Ok(Tape::Fixed(n)),
+                Err(_) => Err(format!(
+                    "invalid tape `{s}`, expected a size, `unbounded` or `bidirectional`"
+                )),
+            },
+        }
+    }
+}
+
+/// What happens when the data pointer moves past an edge of the tape.
+///
+/// A bidirectional tape has no edges, an unbounded tape only the left one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EdgePolicy {
+    /// Continue from the opposite end of a fixed tape. An unbounded tape has
+    /// no end to wrap to, leaving it on the left is an error like with
+    /// [`EdgePolicy::Error`].
+    #[default]
+    Wrap,
+    /// Stop with [`Error::MemoryOutOfBounds`](crate::Error::MemoryOutOfBounds).
+    Error,
+    /// Stay on the edge cell.
+    Clamp,
+}
+
+impl FromStr for EdgePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wrap" => Ok(EdgePolicy::Wrap),
+            "error" => Ok(EdgePolicy::Error),
+            "clamp" => Ok(EdgePolicy::Clamp),
+            _ => Err(format!(
+                "invalid edge policy `{s}`, expected `wrap`, `error` or `clamp`"
+            )),
+        }
+    }
+}
+
+/// Number of bits of a tape cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CellWidth {
+    #[default]
+    U8,
+    U16,
+    U32,
+}
+
+impl FromStr for CellWidth {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "8" => Ok(CellWidth::U8),
+            "16" => Ok(CellWidth::U16),
+            "32" => Ok(CellWidth::U32),
+            _ => Err(format!(
+                "invalid cell width `{s}`, expected `8`, `16` or `32`"
+            )),
+        }
+    }
+}
+
+/// What happens when `+` or `-` takes a cell past its range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    /// Arithmetic modulo the cell size.
+    #[default]
+    Wrap,
+    /// Stay on the minimum or maximum value.
+    Saturate,
+    /// Stop with [`Error::CellOverflow`](crate::Error::CellOverflow).
+    Trap,
+}
+
+impl FromStr for Overflow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wrap" => Ok(Overflow::Wrap),
+            "saturate" => Ok(Overflow::Saturate),
+            "trap" => Ok(Overflow::Trap),
+            _ => Err(format!(
+                "invalid overflow mode `{s}`, expected `wrap`, `saturate` or `trap`"
+            )),
+        }
+    }
+}
+
+/// How `.` turns a cell into output bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputMode {
+    /// The low byte of the cell, unchanged.
+    #[default]
+    Byte,
+    /// The cell as a Unicode code point encoded in UTF-8.
+    Utf8,
+    /// The value of the cell in decimal, followed by a newline.
+    Decimal,
+}
+
+impl FromStr for OutputMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "byte" => Ok(OutputMode::Byte),
+            "utf8" => Ok(OutputMode::Utf8),
+            "decimal" => Ok(OutputMode::Decimal),
+            _ => Err(format!(
+                "invalid output mode `{s}`, expected `byte`, `utf8` or `decimal`"
+            )),
+        }
+    }
+}
+
+/// What `,` stores in the cell once the input is exhausted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EofPolicy {
+    #[default]
+    Zero,
+    /// The maximum value of the cell, -1 for signed interpretations.
+    MinusOne,
+    /// The cell keeps its value.
+    Unchanged,
+}
+
+impl FromStr for EofPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "0" | "zero" => Ok(EofPolicy::Zero),
+            "-1" | "minus-one" => Ok(EofPolicy::MinusOne),
+            "unchanged" => Ok(EofPolicy::Unchanged),
+            _ => Err(format!(
+                "invalid EOF policy `{s}`, expected `0`, `-1` or `unchanged`"
+            )),
+        }
+    }
+}
+
+/// Whether the output is flushed before `,` reads input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Flush {
+    /// Before every `,`, so that a prompt is shown before the program waits
+    /// for the answer.
+    #[default]
+    BeforeInput,
+    /// Only when the output buffer fills up and at the end, for programs
+    /// reading from a pipe or a file.
+    AtExit,
+}
+
+impl FromStr for Flush {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "input" => Ok(Flush::BeforeInput),
+            "exit" => Ok(Flush::AtExit),
+            _ => Err(format!(
+                "invalid flush mode `{s}`, expected `input` or `exit`"
+            )),
+        }
+    }
+}
+
+/// Resources a run may use before the interpreter stops it, `None` is no
+/// limit.
+///
+/// Steps, time and output are counted from the start of each call to
+/// [`run`](crate::Interpreter::run), [`execute`](crate::Interpreter::execute)
+/// or [`profile`](crate::Interpreter::profile). The generated code of the
+/// backends does not enforce them, the JIT leaves them to the interpreter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Limits {
+    /// Instructions executed, after optimization. A loop counts one step per
+    /// check of its condition, a loop that looks for a zero cell one step per
+    /// cell it checks.
+    pub steps: Option<u64>,
+    /// Wall-clock time, checked every few thousand steps.
+    pub time: Option<Duration>,
+    /// Bytes written to the output.
+    pub output: Option<u64>,
+    /// Cells a growing tape can reach.
+    pub tape: Option<usize>,
+}
+
+/// Settings of an [`Interpreter`](crate::Interpreter).
+///
+/// `cell` is only used by backends that generate code, an interpreter
+/// always uses the width of its cell type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Options {
+    pub tape: Tape,
+    pub edge: EdgePolicy,
+    pub cell: CellWidth,
+    pub overflow: Overflow,
+    pub output: OutputMode,
+    pub eof: EofPolicy,
+    pub flush: Flush,
+    pub limits: Limits,
+}
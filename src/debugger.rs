@@ -0,0 +1,213 @@
+use std::{
+    collections::BTreeSet,
+    io::{Read, Write},
+};
+
+use crate::{
+    bytecode::{lower, Instruction, Program},
+    cell::Cell,
+    config::Options,
+    error::Error,
+    interpreter::Interpreter,
+    parser::parse,
+    token::Span,
+};
+
+/// Why the debugger gave back control.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// The requested instructions were executed.
+    Step,
+    /// The next instruction has a breakpoint.
+    Breakpoint,
+    /// A watched cell changed, or got the watched value.
+    Watchpoint { position: isize },
+    /// There are no more instructions.
+    Finished,
+}
+
+struct Watchpoint<C> {
+    position: isize,
+    /// Stops only when the cell becomes this value.
+    value: Option<u32>,
+    last: Option<C>,
+}
+
+/// Executes a program one instruction at a time, stopping at breakpoints and
+/// watchpoints.
+///
+/// The program is not optimized, so every instruction is a single command of
+/// the source, or a bracket.
+pub struct Debugger<C: Cell = u8> {
+    source: String,
+    interpreter: Interpreter<C>,
+    program: Program,
+    pc: usize,
+    breakpoints: BTreeSet<usize>,
+    watchpoints: Vec<Watchpoint<C>>,
+    /// Control was given back before the next instruction, so its breakpoint
+    /// doesn't stop the debugger again.
+    stopped: bool,
+}
+
+impl<C: Cell> Debugger<C> {
+    pub fn new(source: &str, options: Options) -> Result<Self, Error> {
+        Ok(Self {
+            program: lower(&parse(source)?),
+            source: source.to_string(),
+            interpreter: Interpreter::new(options),
+            pc: 0,
+            breakpoints: BTreeSet::new(),
+            watchpoints: Vec::new(),
+            stopped: false,
+        })
+    }
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+    pub fn interpreter(&self) -> &Interpreter<C> {
+        &self.interpreter
+    }
+    /// Span of the next instruction, `None` once the program finished.
+    pub fn span(&self) -> Option<Span> {
+        self.program.spans.get(self.pc).copied()
+    }
+    /// Adds a breakpoint on the first instruction at or after the source
+    /// byte `offset`, returns its span.
+    pub fn add_breakpoint(&mut self, offset: usize) -> Option<Span> {
+        let pc = self
+            .program
+            .spans
+            .iter()
+            .position(|span| span.start >= offset)?;
+        self.breakpoints.insert(pc);
+        Some(self.program.spans[pc])
+    }
+    /// Removes the breakpoint that [`add_breakpoint`](Self::add_breakpoint)
+    /// would add for `offset`, returns whether there was one.
+    pub fn remove_breakpoint(&mut self, offset: usize) -> bool {
+        match self
+            .program
+            .spans
+            .iter()
+            .position(|span| span.start >= offset)
+        {
+            Some(pc) => self.breakpoints.remove(&pc),
+            None => false,
+        }
+    }
+    /// Adds a breakpoint for every `#` in the source, returns how many.
+    pub fn break_at_markers(&mut self) -> usize {
+        let markers: Vec<usize> = self
+            .source
+            .bytes()
+            .enumerate()
+            .filter(|&(_, byte)| byte == b'#')
+            .map(|(offset, _)| offset)
+            .collect();
+        markers
+            .into_iter()
+            .filter(|&offset| self.add_breakpoint(offset).is_some())
+            .count()
+    }
+    /// Stops when the cell at `position` changes, or only when it becomes
+    /// `value` if given.
+    pub fn watch(&mut self, position: isize, value: Option<u32>) {
+        self.unwatch(position);
+        self.watchpoints.push(Watchpoint {
+            position,
+            value,
+            last: self.interpreter.cell(position),
+        });
+    }
+    /// Removes the watchpoint on the cell at `position`, returns whether there was one.
+    pub fn unwatch(&mut self, position: isize) -> bool {
+        let len = self.watchpoints.len();
+        self.watchpoints.retain(|watch| watch.position != position);
+        self.watchpoints.len() != len
+    }
+    /// Executes one instruction, unless it has a breakpoint the debugger did
+    /// not stop at yet.
+    ///
+    /// Returns the reason to stop, or `None` if there is none.
+    fn advance<R: Read, W: Write>(
+        &mut self,
+        input: &mut R,
+        output: &mut W,
+    ) -> Result<Option<Stop>, Error> {
+        if self.pc >= self.program.instructions.len() {
+            return Ok(Some(Stop::Finished));
+        }
+        if !self.stopped && self.breakpoints.contains(&self.pc) {
+            return Ok(Some(Stop::Breakpoint));
+        }
+        self.stopped = false;
+        self.pc =
+            self.interpreter
+                .execute_at(&self.program.instructions, self.pc, input, output)?;
+        for watch in &mut self.watchpoints {
+            let value = self.interpreter.cell(watch.position);
+            if value != watch.last {
+                watch.last = value;
+                if watch.value.is_none() || value.map(C::to_u32) == watch.value {
+                    return Ok(Some(Stop::Watchpoint {
+                        position: watch.position,
+                    }));
+                }
+            }
+        }
+        if self.pc >= self.program.instructions.len() {
+            return Ok(Some(Stop::Finished));
+        }
+        Ok(None)
+    }
+    /// Gives back control before the next instruction.
+    fn stop(&mut self, stop: Option<Stop>) -> Stop {
+        self.stopped = true;
+        match stop {
+            Some(stop) => stop,
+            None if self.breakpoints.contains(&self.pc) => Stop::Breakpoint,
+            None => Stop::Step,
+        }
+    }
+    /// Executes the next instruction, even if it has a breakpoint.
+    pub fn step<R: Read, W: Write>(
+        &mut self,
+        input: &mut R,
+        output: &mut W,
+    ) -> Result<Stop, Error> {
+        self.stopped = true;
+        let stop = self.advance(input, output)?;
+        Ok(self.stop(stop))
+    }
+    /// Executes the next instruction, or the whole loop if it is a `[`.
+    pub fn step_over<R: Read, W: Write>(
+        &mut self,
+        input: &mut R,
+        output: &mut W,
+    ) -> Result<Stop, Error> {
+        let end = match self.program.instructions.get(self.pc) {
+            Some(&Instruction::JumpIfZero(end)) => end,
+            _ => return self.step(input, output),
+        };
+        self.stopped = true;
+        loop {
+            let stop = self.advance(input, output)?;
+            if stop.is_some() || self.pc == end {
+                return Ok(self.stop(stop));
+            }
+        }
+    }
+    /// Executes until a breakpoint, a watchpoint or the end of the program.
+    pub fn resume<R: Read, W: Write>(
+        &mut self,
+        input: &mut R,
+        output: &mut W,
+    ) -> Result<Stop, Error> {
+        loop {
+            if let Some(stop) = self.advance(input, output)? {
+                return Ok(self.stop(Some(stop)));
+            }
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use std::fmt::{self, Write};
+
+use crate::token::Span;
+
+/// Extra help attached to a [`Diagnostic`], optionally pointing at another
+/// part of the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hint {
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+/// A problem found in the source code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Span,
+    /// Line and column, starting from 1, of the start of the span.
+    pub line: u32,
+    pub column: u32,
+    /// Short explanation shown under the span.
+    pub label: String,
+    pub hint: Option<Hint>,
+}
+
+impl Diagnostic {
+    /// Renders the diagnostic with source excerpts and underlines:
+    ///
+    /// ```
+    /// # use mindfuck::{parse, Error};
+    /// let source = "+[>+";
+    /// let Err(Error::Syntax(diagnostics)) = parse(source) else { unreachable!() };
+    /// assert_eq!(
+    ///     diagnostics[0].render(source, "program.b"),
+    ///     concat!(
+    ///         "unmatched `[`\n",
+    ///         " --> program.b:1:2\n",
+    ///         " 1 | +[>+\n",
+    ///         "   |  ^ this loop is never closed\n",
+    ///         " = hint: add a `]` after the end of the loop",
+    ///     )
+    /// );
+    /// ```
+    pub fn render(&self, source: &str, path: &str) -> String {
+        let mut text = format!(
+            "{}\n --> {path}:{}:{}\n{} {}",
+            self.message,
+            self.line,
+            self.column,
+            self.span.snippet(source),
+            self.label
+        );
+        if let Some(hint) = &self.hint {
+            write!(text, "\n = hint: {}", hint.message).unwrap();
+            if let Some(span) = hint.span {
+                write!(text, "\n{}", span.snippet(source)).unwrap();
+            }
+        }
+        text
+    }
+    /// The diagnostic as a single line JSON object, for editors and tools.
+    pub fn to_json(&self, source: &str, path: &str) -> String {
+        let mut json = format!(
+            "{{\"severity\":\"error\",\"file\":{},\"message\":{},\"label\":{},{}",
+            json_string(path),
+            json_string(&self.message),
+            json_string(&self.label),
+            json_location(self.span, self.line, self.column)
+        );
+        match &self.hint {
+            Some(hint) => {
+                write!(
+                    json,
+                    ",\"hint\":{{\"message\":{}",
+                    json_string(&hint.message)
+                )
+                .unwrap();
+                if let Some(span) = hint.span {
+                    let (line, column) = span.line_column(source);
+                    write!(json, ",{}", json_location(span, line, column)).unwrap();
+                }
+                json.push_str("}}");
+            }
+            None => json.push_str(",\"hint\":null}"),
+        }
+        json
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} on line: {}:{}", self.message, self.line, self.column)
+    }
+}
+
+fn json_location(span: Span, line: u32, column: u32) -> String {
+    format!(
+        "\"line\":{line},\"column\":{column},\"start\":{},\"end\":{}",
+        span.start, span.end
+    )
+}
+
+fn json_string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
@@ -0,0 +1,111 @@
+use std::{fmt, time::Duration};
+
+use crate::{diagnostic::Diagnostic, token::Span};
+
+#[derive(Debug)]
+pub enum Error {
+    /// Every unmatched `[` and `]` of the source, in order.
+    Syntax(Vec<Diagnostic>),
+    /// Reading the input or writing the output failed.
+    Io(std::io::Error),
+    /// The data pointer left the tape, `dp` is the first cell past the edge
+    /// it crossed.
+    MemoryOutOfBounds { dp: isize },
+    /// A `+` or `-` overflowed a cell while overflow traps.
+    CellOverflow { dp: isize },
+    /// The options or the program are not supported by a backend.
+    Unsupported(&'static str),
+    /// The program executed more instructions than allowed.
+    StepLimitExceeded { limit: u64 },
+    /// The program ran for longer than allowed.
+    TimeLimitExceeded { limit: Duration },
+    /// The program tried to write more bytes than allowed.
+    OutputLimitExceeded { limit: u64 },
+    /// A growing tape needed more cells than allowed.
+    TapeLimitExceeded { limit: usize },
+    /// A snapshot can't be restored, it is damaged or of another version or
+    /// cell width.
+    InvalidSnapshot(&'static str),
+    /// A runtime error raised by the token or instruction at `span`.
+    Located { span: Span, error: Box<Error> },
+}
+
+impl Error {
+    /// Attaches the span of the failing token, unless the error already has one.
+    pub(crate) fn at(self, span: Span) -> Self {
+        match self {
+            Error::Located { .. } => self,
+            error => Error::Located {
+                span,
+                error: Box::new(error),
+            },
+        }
+    }
+    /// Source span of the token that raised the error, if known.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Error::Located { span, .. } => Some(*span),
+            _ => None,
+        }
+    }
+    /// The error without its location.
+    pub fn inner(&self) -> &Error {
+        match self {
+            Error::Located { error, .. } => error,
+            error => error,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Syntax(diagnostics) => {
+                for (i, diagnostic) in diagnostics.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", diagnostic)?;
+                }
+                Ok(())
+            }
+            Error::Io(e) => write!(f, "error while reading or writing data: {}", e),
+            Error::MemoryOutOfBounds { dp } => {
+                write!(f, "the data pointer moved out of the tape: {}", dp)
+            }
+            Error::CellOverflow { dp } => write!(f, "overflow of the cell {}", dp),
+            Error::Unsupported(reason) => write!(f, "unsupported: {}", reason),
+            Error::StepLimitExceeded { limit } => {
+                write!(f, "the program executed more than {} instructions", limit)
+            }
+            Error::TimeLimitExceeded { limit } => {
+                write!(f, "the program ran for more than {:?}", limit)
+            }
+            Error::OutputLimitExceeded { limit } => {
+                write!(f, "the program wrote more than {} bytes", limit)
+            }
+            Error::TapeLimitExceeded { limit } => {
+                write!(f, "the tape grew beyond {} cells", limit)
+            }
+            Error::InvalidSnapshot(reason) => write!(f, "invalid snapshot: {}", reason),
+            // The location needs the source code, see `Span::line_column`
+            Error::Located { error, .. } => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Located { error, .. } => error.source(),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
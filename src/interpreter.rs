@@ -0,0 +1,538 @@
+use std::{
+    io::{Read, Write},
+    time::Instant,
+};
+
+use crate::{
+    bytecode::{Instruction, Program},
+    cell::Cell,
+    config::{EdgePolicy, EofPolicy, Flush, Limits, Options, Overflow, Tape},
+    error::Error,
+    output::{encoded_len, OutputSink},
+    token::{Token, TokenKind},
+};
+
+pub const MEMORY_SIZE: usize = 30_000;
+
+/// Steps between two checks of the time limit.
+const TIME_CHECK_INTERVAL: u64 = 4096;
+
+#[derive(Clone)]
+pub struct Interpreter<C: Cell = u8> {
+    pub(crate) options: Options,
+    pub(crate) memory: Vec<C>,
+    pub(crate) dp: usize,     // Data Pointer
+    pub(crate) origin: usize, // Index of cell 0 inside `memory`, moves when a bidirectional tape grows left
+    // Usage of the current run, checked against `options.limits`
+    pub(crate) steps: u64,
+    pub(crate) written: u64,
+    deadline: Option<Instant>,
+    checkpoint: u64,
+}
+impl<C: Cell> Interpreter<C> {
+    pub fn new(mut options: Options) -> Self {
+        options.cell = C::WIDTH;
+        let size = match options.tape {
+            // A tape without cells would have no cell to point at
+            Tape::Fixed(size) => {
+                options.tape = Tape::Fixed(size.max(1));
+                size.max(1)
+            }
+            Tape::Unbounded | Tape::Bidirectional => 1,
+        };
+        let mut interpreter = Self {
+            options,
+            memory: vec![C::default(); size],
+            dp: 0,
+            origin: 0,
+            steps: 0,
+            written: 0,
+            deadline: None,
+            checkpoint: 0,
+        };
+        interpreter.checkpoint = interpreter.checkpoint();
+        interpreter
+    }
+    /// Position of the data pointer relative to cell 0.
+    pub fn dp(&self) -> isize {
+        self.dp as isize - self.origin as isize
+    }
+    fn write_memory(&mut self, value: C) {
+        self.memory[self.dp] = value
+    }
+    fn read_memory(&self) -> C {
+        self.memory[self.dp]
+    }
+    fn write_output<W: Write>(&mut self, offset: isize, output: &mut W) -> Result<(), Error> {
+        let index = self.locate(offset)?;
+        let value = self.memory[index].to_u32();
+        let bytes = encoded_len(self.options.output, value) as u64;
+        if let Some(limit) = self.options.limits.output {
+            if self.written + bytes > limit {
+                return Err(Error::OutputLimitExceeded { limit });
+            }
+        }
+        self.written += bytes;
+        OutputSink::new(self.options.output, output).write_cell(value)
+    }
+    fn read_cell<R: Read, W: Write>(
+        &mut self,
+        offset: isize,
+        input: &mut R,
+        output: &mut W,
+    ) -> Result<(), Error> {
+        let index = self.locate(offset)?;
+        self.memory[index] = read_value(&self.options, self.memory[index], input, output)?;
+        Ok(())
+    }
+    /// Adds `v` to the cell at `offset`, starting from zero if `set` is true.
+    fn add_value(&mut self, offset: isize, v: isize, set: bool) -> Result<(), Error> {
+        let index = self.locate(offset)?;
+        let value = if set {
+            C::default()
+        } else {
+            self.memory[index]
+        };
+        match value.add(v, self.options.overflow) {
+            Some(value) => self.memory[index] = value,
+            None => {
+                // The cell that overflowed, after the edge policy
+                return Err(Error::CellOverflow {
+                    dp: index as isize - self.origin as isize,
+                });
+            }
+        }
+        Ok(())
+    }
+    /// Resets the usage counted against the limits, at the start of a run.
+    pub(crate) fn start(&mut self) {
+        self.steps = 0;
+        self.written = 0;
+        self.start_clock();
+    }
+    /// Starts counting the time limit from now.
+    pub(crate) fn start_clock(&mut self) {
+        self.deadline = self
+            .options
+            .limits
+            .time
+            .and_then(|limit| Instant::now().checked_add(limit));
+        self.checkpoint = self.checkpoint();
+    }
+    /// Step count after which the step and time limits are checked next.
+    fn checkpoint(&self) -> u64 {
+        let time = match self.deadline {
+            Some(_) => self.steps.saturating_add(TIME_CHECK_INTERVAL),
+            None => u64::MAX,
+        };
+        self.options.limits.steps.unwrap_or(u64::MAX).min(time)
+    }
+    /// Counts an executed instruction, fails if the step or time limit is
+    /// exceeded.
+    #[inline]
+    fn count_step(&mut self) -> Result<(), Error> {
+        self.steps += 1;
+        if self.steps > self.checkpoint {
+            return self.check_limits();
+        }
+        Ok(())
+    }
+    #[cold]
+    fn check_limits(&mut self) -> Result<(), Error> {
+        if let Some(limit) = self.options.limits.steps {
+            if self.steps > limit {
+                return Err(Error::StepLimitExceeded { limit });
+            }
+        }
+        if let (Some(deadline), Some(limit)) = (self.deadline, self.options.limits.time) {
+            if Instant::now() >= deadline {
+                return Err(Error::TimeLimitExceeded { limit });
+            }
+        }
+        self.checkpoint = self.checkpoint();
+        Ok(())
+    }
+    /// Index in `memory` of the cell `v` cells away from the data pointer,
+    /// growing the tape or applying the edge policy.
+    fn locate(&mut self, v: isize) -> Result<usize, Error> {
+        let len = self.memory.len();
+        let index = match (self.dp.checked_add_signed(v), self.options.tape) {
+            (Some(index), Tape::Fixed(_)) if index < len => index,
+            (Some(index), Tape::Unbounded | Tape::Bidirectional) => {
+                if index >= len {
+                    let limit = self.tape_limit(index + 1)?;
+                    self.memory
+                        .resize((index + 1).max(len * 2).min(limit), C::default());
+                }
+                index
+            }
+            (None, Tape::Bidirectional) => {
+                // Grow on the left by at least the current length
+                let missing = v.unsigned_abs() - self.dp;
+                let limit = self.tape_limit(len.saturating_add(missing))?;
+                let grow = missing.max(len).min(limit - len);
+                self.memory
+                    .splice(0..0, std::iter::repeat_n(C::default(), grow));
+                self.origin += grow;
+                self.dp += grow;
+                grow - missing
+            }
+            (_, _) => match self.options.edge {
+                // An unbounded tape has no fixed end to wrap to
+                EdgePolicy::Wrap if matches!(self.options.tape, Tape::Fixed(_)) => {
+                    let index = (self.dp as isize).wrapping_add(v);
+                    index.rem_euclid(len as isize) as usize
+                }
+                EdgePolicy::Clamp => {
+                    if v < 0 {
+                        0
+                    } else {
+                        len - 1
+                    }
+                }
+                EdgePolicy::Wrap | EdgePolicy::Error => {
+                    // Only fixed and unbounded tapes get here, their origin is 0
+                    let dp = if v < 0 { -1 } else { len as isize };
+                    return Err(Error::MemoryOutOfBounds { dp });
+                }
+            },
+        };
+        Ok(index)
+    }
+    /// Maximum length of the tape, fails if it is less than `needed`.
+    fn tape_limit(&self, needed: usize) -> Result<usize, Error> {
+        match self.options.limits.tape {
+            Some(limit) if needed > limit => Err(Error::TapeLimitExceeded { limit }),
+            limit => Ok(limit.unwrap_or(usize::MAX)),
+        }
+    }
+    fn move_dp(&mut self, v: isize) -> Result<(), Error> {
+        self.dp = self.locate(v)?;
+        Ok(())
+    }
+    /// Adds the current cell times `factor` to the cell at `offset`.
+    fn mul_add(&mut self, offset: isize, factor: isize) -> Result<(), Error> {
+        let value = self.read_memory().to_u32() as isize;
+        if value == 0 {
+            return Ok(());
+        }
+        let product = match self.options.overflow {
+            Overflow::Wrap => value.wrapping_mul(factor),
+            _ => value.saturating_mul(factor),
+        };
+        let index = self.locate(offset)?;
+        match self.memory[index].add(product, self.options.overflow) {
+            Some(value) => self.memory[index] = value,
+            None => {
+                // The cell that overflowed, after the edge policy
+                return Err(Error::CellOverflow {
+                    dp: index as isize - self.origin as isize,
+                });
+            }
+        }
+        Ok(())
+    }
+    /// Moves by `step` until a zero cell, counting a step for every move
+    /// like the loop it replaces.
+    fn scan(&mut self, step: isize) -> Result<(), Error> {
+        while self.read_memory() != C::default() {
+            self.count_step()?;
+            self.move_dp(step)?
+        }
+        Ok(())
+    }
+    /// Executes the tokens, reading `,` from `input` and writing `.` to `output`.
+    ///
+    /// Runtime errors are [`Error::Located`] at the span of the failing token.
+    pub fn run<R: Read, W: Write>(
+        &mut self,
+        istructions: &[Token],
+        input: &mut R,
+        output: &mut W,
+    ) -> Result<(), Error> {
+        self.start();
+        self.run_tokens(istructions, input, output)
+    }
+    fn run_tokens<R: Read, W: Write>(
+        &mut self,
+        istructions: &[Token],
+        input: &mut R,
+        output: &mut W,
+    ) -> Result<(), Error> {
+        // The tokens of every loop being run with the position of the next
+        // one, so that nested loops don't grow the call stack
+        let mut stack: Vec<(&[Token], usize)> = vec![(istructions, 0)];
+        while let Some(&(tokens, position)) = stack.last() {
+            let Some(token) = tokens.get(position) else {
+                // Back to the loop, which checks its cell again
+                stack.pop();
+                continue;
+            };
+            match self
+                .run_token(token, input, output)
+                .map_err(|e| e.at(token.span))?
+            {
+                Some(body) => stack.push((body, 0)),
+                None => {
+                    if let Some((_, position)) = stack.last_mut() {
+                        *position += 1
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Runs a token, returns the body of a loop to run next.
+    fn run_token<'a, R: Read, W: Write>(
+        &mut self,
+        token: &'a Token,
+        input: &mut R,
+        output: &mut W,
+    ) -> Result<Option<&'a [Token]>, Error> {
+        self.count_step()?;
+        match &token.kind {
+            TokenKind::Output => self.write_output(0, output)?,
+            TokenKind::Input => self.read_cell(0, input, output)?,
+            TokenKind::Move(v) => self.move_dp(*v)?,
+            TokenKind::IncValue(v) => self.add_value(0, *v, false)?,
+            TokenKind::SetZero => self.write_memory(C::default()),
+            TokenKind::Add { offset, value } => self.add_value(*offset, *value, false)?,
+            TokenKind::Set { offset, value } => self.add_value(*offset, *value, true)?,
+            TokenKind::OutputAt { offset } => self.write_output(*offset, output)?,
+            TokenKind::InputAt { offset } => self.read_cell(*offset, input, output)?,
+            TokenKind::Scan(step) => self.scan(*step)?,
+            TokenKind::MulAdd { offset, factor } => self.mul_add(*offset, *factor)?,
+            TokenKind::Loop(istr) => {
+                if self.read_memory() != C::default() {
+                    return Ok(Some(istr));
+                }
+            }
+        }
+        Ok(None)
+    }
+    /// Executes bytecode produced by [`lower`](crate::lower).
+    ///
+    /// Same behavior as [`run`](Self::run), without recursing into loops.
+    pub fn execute<R: Read, W: Write>(
+        &mut self,
+        program: &Program,
+        input: &mut R,
+        output: &mut W,
+    ) -> Result<(), Error> {
+        self.start();
+        if self.options.limits == Limits::default()
+            && matches!(self.options.tape, Tape::Fixed(_))
+            && self.options.overflow == Overflow::Wrap
+        {
+            return self.execute_unlimited(program, input, output);
+        }
+        let mut pc = 0; // Program Counter
+        while pc < program.instructions.len() {
+            pc = self
+                .execute_at(&program.instructions, pc, input, output)
+                .map_err(|e| e.at(program.spans[pc]))?;
+        }
+        Ok(())
+    }
+    /// [`execute`](Self::execute) on a fixed tape of wrapping cells without
+    /// limits, where nothing needs to be counted.
+    fn execute_unlimited<R: Read, W: Write>(
+        &mut self,
+        program: &Program,
+        input: &mut R,
+        output: &mut W,
+    ) -> Result<(), Error> {
+        let instructions = &program.instructions[..];
+        let mut pc = 0;
+        while pc < instructions.len() {
+            pc = self.execute_inside(instructions, pc);
+            if pc < instructions.len() {
+                pc = self
+                    .execute_at(instructions, pc, input, output)
+                    .map_err(|e| e.at(program.spans[pc]))?;
+            }
+        }
+        Ok(())
+    }
+    /// Executes the instructions from `pc` with the data pointer in a local,
+    /// up to the end or the first one that does I/O or reaches past an edge
+    /// of the tape. Returns the pc of that instruction.
+    fn execute_inside(&mut self, program: &[Instruction], mut pc: usize) -> usize {
+        let memory = &mut self.memory[..];
+        let mut dp = self.dp;
+        let len = memory.len();
+        // Index of the cell `offset` cells away, `None` past an edge
+        let cell =
+            |dp: usize, offset: isize| dp.checked_add_signed(offset).filter(|&index| index < len);
+        while let Some(&instruction) = program.get(pc) {
+            match instruction {
+                Instruction::Move(v) => match cell(dp, v) {
+                    Some(index) => dp = index,
+                    None => break,
+                },
+                Instruction::IncValue(v) => {
+                    memory[dp] = memory[dp].add(v, Overflow::Wrap).unwrap_or_default()
+                }
+                Instruction::SetZero => memory[dp] = C::default(),
+                Instruction::Add { offset, value } => match cell(dp, offset) {
+                    Some(index) => {
+                        memory[index] = memory[index].add(value, Overflow::Wrap).unwrap_or_default()
+                    }
+                    None => break,
+                },
+                Instruction::Set { offset, value } => match cell(dp, offset) {
+                    Some(index) => {
+                        memory[index] = C::default().add(value, Overflow::Wrap).unwrap_or_default()
+                    }
+                    None => break,
+                },
+                Instruction::MulAdd { offset, factor } => {
+                    let value = memory[dp].to_u32() as isize;
+                    if value != 0 {
+                        match cell(dp, offset) {
+                            Some(index) => {
+                                memory[index] = memory[index]
+                                    .add(value.wrapping_mul(factor), Overflow::Wrap)
+                                    .unwrap_or_default()
+                            }
+                            None => break,
+                        }
+                    }
+                }
+                Instruction::Scan(step) => {
+                    if memory[dp] != C::default() {
+                        match cell(dp, step) {
+                            Some(index) => dp = index,
+                            None => break,
+                        }
+                        continue;
+                    }
+                }
+                Instruction::JumpIfZero(target) => {
+                    if memory[dp] == C::default() {
+                        pc = target;
+                        continue;
+                    }
+                }
+                Instruction::JumpIfNotZero(target) => {
+                    if memory[dp] != C::default() {
+                        pc = target;
+                        continue;
+                    }
+                }
+                Instruction::Output
+                | Instruction::Input
+                | Instruction::OutputAt { .. }
+                | Instruction::InputAt { .. } => break,
+            }
+            pc += 1;
+        }
+        self.dp = dp;
+        pc
+    }
+    /// Executes the instruction at `pc`, returns the pc of the next one.
+    #[inline]
+    pub(crate) fn execute_at<R: Read, W: Write>(
+        &mut self,
+        program: &[Instruction],
+        pc: usize,
+        input: &mut R,
+        output: &mut W,
+    ) -> Result<usize, Error> {
+        self.count_step()?;
+        match program[pc] {
+            Instruction::Output => self.write_output(0, output)?,
+            Instruction::Input => self.read_cell(0, input, output)?,
+            Instruction::Move(v) => self.move_dp(v)?,
+            Instruction::IncValue(v) => self.add_value(0, v, false)?,
+            Instruction::SetZero => self.write_memory(C::default()),
+            Instruction::Add { offset, value } => self.add_value(offset, value, false)?,
+            Instruction::Set { offset, value } => self.add_value(offset, value, true)?,
+            Instruction::OutputAt { offset } => self.write_output(offset, output)?,
+            Instruction::InputAt { offset } => self.read_cell(offset, input, output)?,
+            // One move at a time, so that the limits are checked and the
+            // callers can pause in the middle of a long scan
+            Instruction::Scan(step) => {
+                if self.read_memory() != C::default() {
+                    self.move_dp(step)?;
+                    return Ok(pc);
+                }
+            }
+            Instruction::MulAdd { offset, factor } => self.mul_add(offset, factor)?,
+            Instruction::JumpIfZero(target) => {
+                if self.read_memory() == C::default() {
+                    return Ok(target);
+                }
+            }
+            Instruction::JumpIfNotZero(target) => {
+                if self.read_memory() != C::default() {
+                    return Ok(target);
+                }
+            }
+        }
+        Ok(pc + 1)
+    }
+    /// Value of the cell at `position` relative to cell 0, `None` if the
+    /// cell is outside the tape.
+    pub fn cell(&self, position: isize) -> Option<C> {
+        let index = position.checked_add(self.origin as isize)?;
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| self.memory.get(index).copied())
+            .or_else(|| match self.options.tape {
+                // Growing tapes are zero beyond what has been allocated so far
+                Tape::Unbounded if index >= 0 => Some(C::default()),
+                Tape::Bidirectional => Some(C::default()),
+                _ => None,
+            })
+    }
+    /// Sets the cell at `position` relative to cell 0, growing the tape if
+    /// needed. Fails if the cell is outside a fixed tape.
+    pub fn set_cell(&mut self, position: isize, value: C) -> Result<(), Error> {
+        let offset = match (self.cell(position), position.checked_sub(self.dp())) {
+            (Some(_), Some(offset)) => offset,
+            _ => return Err(Error::MemoryOutOfBounds { dp: position }),
+        };
+        let index = self.locate(offset)?;
+        self.memory[index] = value;
+        Ok(())
+    }
+}
+
+impl<C: Cell> Default for Interpreter<C> {
+    fn default() -> Self {
+        Self::new(Options::default())
+    }
+}
+
+/// Reads the new value of a cell whose value is `current`, applying the EOF
+/// and flush policies.
+pub(crate) fn read_value<C: Cell, R: Read + ?Sized, W: Write + ?Sized>(
+    options: &Options,
+    current: C,
+    input: &mut R,
+    output: &mut W,
+) -> Result<C, Error> {
+    if options.flush == Flush::BeforeInput {
+        output.flush()?;
+    }
+    Ok(match (read_input(input)?, options.eof) {
+        (Some(byte), _) => C::from_u8(byte),
+        (None, EofPolicy::Zero) => C::default(),
+        (None, EofPolicy::MinusOne) => C::MAX,
+        (None, EofPolicy::Unchanged) => current,
+    })
+}
+
+/// Reads one byte, `None` at the end of the input.
+fn read_input<R: Read + ?Sized>(input: &mut R) -> Result<Option<u8>, std::io::Error> {
+    let mut buffer = [0];
+    loop {
+        match input.read(&mut buffer) {
+            Ok(0) => return Ok(None),
+            Ok(_) => return Ok(Some(buffer[0])),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e),
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use std::io::{Read, Write};
+
+use crate::{error::Error, interpreter::Interpreter, token::Token};
+
+impl Interpreter<u8> {
+    /// Compiles the tokens to native code and runs them.
+    ///
+    /// Only x86-64 Linux with a fixed tape, wrapping cells and no limits is
+    /// compiled, everything else falls back to the bytecode interpreter.
+    pub fn run_jit<R: Read, W: Write>(
+        &mut self,
+        istructions: &[Token],
+        input: &mut R,
+        output: &mut W,
+    ) -> Result<(), Error> {
+        #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+        if let Some((code, spans)) = x86_64::compile(istructions, &self.options) {
+            return x86_64::run(self, &code, &spans, input, output);
+        }
+        self.execute(&crate::bytecode::lower(istructions), input, output)
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+mod x86_64 {
+    use std::{
+        ffi::{c_int, c_void},
+        io::{Read, Write},
+    };
+
+    use crate::{
+        backend::x86_64::{Assembler, Routine, Routines},
+        config::{EdgePolicy, Limits, Options, Overflow, Tape},
+        error::Error,
+        interpreter::{read_value, Interpreter},
+        output::OutputSink,
+        token::{Span, Token},
+    };
+
+    extern "C" {
+        fn mmap(
+            addr: *mut c_void,
+            len: usize,
+            prot: c_int,
+            flags: c_int,
+            fd: c_int,
+            offset: i64,
+        ) -> *mut c_void;
+        fn mprotect(addr: *mut c_void, len: usize, prot: c_int) -> c_int;
+        fn munmap(addr: *mut c_void, len: usize) -> c_int;
+    }
+
+    const PROT_READ: c_int = 1;
+    const PROT_WRITE: c_int = 2;
+    const PROT_EXEC: c_int = 4;
+    const MAP_PRIVATE: c_int = 2;
+    const MAP_ANONYMOUS: c_int = 0x20;
+
+    /// State shared with the helpers called by the generated code.
+    struct Context<'a> {
+        options: Options,
+        base: *mut u8,
+        len: usize,
+        input: &'a mut dyn Read,
+        output: &'a mut dyn Write,
+        /// Spans of the tokens calling the helpers, indexed by their argument.
+        spans: &'a [Span],
+        error: Option<Error>,
+        /// Data pointer of the token that failed.
+        dp: usize,
+    }
+
+    // The generated code keeps the context in r14 and passes it to the helpers in rdi
+
+    impl Context<'_> {
+        /// Records the error of the token calling a helper and where the data
+        /// pointer was.
+        fn fail(&mut self, error: Error, at: u32, dp: *const u8) {
+            self.error = Some(error.at(self.spans[at as usize]));
+            self.dp = dp as usize - self.base as usize;
+        }
+    }
+
+    /// Signature of the generated code: returns the final data pointer, null on error.
+    type Entry = unsafe extern "C" fn(
+        ctx: *mut Context,
+        base: *mut u8,
+        end: *mut u8,
+        ptr: *mut u8,
+    ) -> *mut u8;
+
+    extern "C" fn jit_output(ctx: *mut Context, cell: *const u8, at: u32, dp: *const u8) -> u8 {
+        let ctx = unsafe { &mut *ctx };
+        let value = unsafe { *cell };
+        match OutputSink::new(ctx.options.output, &mut *ctx.output).write_cell(value as u32) {
+            Ok(()) => 0,
+            Err(e) => {
+                ctx.fail(e, at, dp);
+                1
+            }
+        }
+    }
+
+    extern "C" fn jit_input(ctx: *mut Context, cell: *mut u8, at: u32, dp: *const u8) -> u8 {
+        let ctx = unsafe { &mut *ctx };
+        match read_value(&ctx.options, unsafe { *cell }, ctx.input, ctx.output) {
+            Ok(value) => {
+                unsafe { *cell = value };
+                0
+            }
+            Err(e) => {
+                ctx.fail(e, at, dp);
+                1
+            }
+        }
+    }
+
+    /// Applies the edge policy to an address outside the tape.
+    extern "C" fn jit_locate(ctx: *mut Context, addr: *mut u8, at: u32, dp: *const u8) -> *mut u8 {
+        let ctx = unsafe { &mut *ctx };
+        let index = (addr as isize).wrapping_sub(ctx.base as isize);
+        let index = match ctx.options.edge {
+            EdgePolicy::Wrap => index.rem_euclid(ctx.len as isize) as usize,
+            EdgePolicy::Clamp => {
+                if index < 0 {
+                    0
+                } else {
+                    ctx.len - 1
+                }
+            }
+            EdgePolicy::Error => {
+                let outside = if index < 0 { -1 } else { ctx.len as isize };
+                ctx.fail(Error::MemoryOutOfBounds { dp: outside }, at, dp);
+                return std::ptr::null_mut();
+            }
+        };
+        unsafe { ctx.base.add(index) }
+    }
+
+    /// Generates the machine code of the program with the spans of its helper
+    /// calls, `None` if the options or the program are not supported.
+    pub(super) fn compile(tokens: &[Token], options: &Options) -> Option<(Vec<u8>, Vec<Span>)> {
+        if !matches!(options.tape, Tape::Fixed(_))
+            || options.overflow != Overflow::Wrap
+            || options.limits != Limits::default()
+        {
+            return None;
+        }
+        let mut asm = Assembler::new(Routines {
+            output: Routine::Absolute(jit_output as *const () as usize),
+            input: Routine::Absolute(jit_input as *const () as usize),
+            locate: Routine::Absolute(jit_locate as *const () as usize),
+        });
+        asm.emit(&[0x53, 0x41, 0x54, 0x41, 0x55, 0x41, 0x56, 0x41, 0x57]); // push rbx, r12, r13, r14, r15
+        asm.emit(&[0x49, 0x89, 0xfe]); // mov r14, rdi
+        asm.emit(&[0x49, 0x89, 0xf4]); // mov r12, rsi
+        asm.emit(&[0x49, 0x89, 0xd5]); // mov r13, rdx
+        asm.emit(&[0x48, 0x89, 0xcb]); // mov rbx, rcx
+        asm.tokens(tokens)?;
+        asm.emit(&[0x48, 0x89, 0xd8]); // mov rax, rbx
+        let exit = asm.code.len();
+        asm.emit(&[0x41, 0x5f, 0x41, 0x5e, 0x41, 0x5d, 0x41, 0x5c, 0x5b]); // pop r15, r14, r13, r12, rbx
+        asm.emit(&[0xc3]); // ret
+        let error = asm.code.len();
+        asm.emit(&[0x31, 0xc0]); // xor eax, eax
+        asm.jump_to(&[0xe9], exit); // jmp
+        asm.patch_errors(error);
+        Some((asm.code, asm.spans))
+    }
+
+    /// Executable copy of the generated code.
+    struct Executable {
+        ptr: *mut c_void,
+        len: usize,
+    }
+
+    impl Executable {
+        fn new(code: &[u8]) -> Result<Self, Error> {
+            let len = code.len();
+            let ptr = unsafe {
+                mmap(
+                    std::ptr::null_mut(),
+                    len,
+                    PROT_READ | PROT_WRITE,
+                    MAP_PRIVATE | MAP_ANONYMOUS,
+                    -1,
+                    0,
+                )
+            };
+            if ptr as isize == -1 {
+                return Err(std::io::Error::last_os_error().into());
+            }
+            let executable = Self { ptr, len };
+            unsafe {
+                std::ptr::copy_nonoverlapping(code.as_ptr(), ptr as *mut u8, len);
+                if mprotect(ptr, len, PROT_READ | PROT_EXEC) != 0 {
+                    return Err(std::io::Error::last_os_error().into());
+                }
+            }
+            Ok(executable)
+        }
+    }
+
+    impl Drop for Executable {
+        fn drop(&mut self) {
+            unsafe { munmap(self.ptr, self.len) };
+        }
+    }
+
+    pub(super) fn run<R: Read, W: Write>(
+        interpreter: &mut Interpreter<u8>,
+        code: &[u8],
+        spans: &[Span],
+        input: &mut R,
+        output: &mut W,
+    ) -> Result<(), Error> {
+        let executable = Executable::new(code)?;
+        let entry: Entry = unsafe { std::mem::transmute(executable.ptr) };
+        let range = interpreter.memory.as_mut_ptr_range();
+        let mut ctx = Context {
+            options: interpreter.options,
+            base: range.start,
+            len: interpreter.memory.len(),
+            input,
+            output,
+            spans,
+            error: None,
+            dp: interpreter.dp,
+        };
+        let ptr = unsafe { range.start.add(interpreter.dp) };
+        let end = unsafe { entry(&mut ctx, range.start, range.end, ptr) };
+        if let Some(e) = ctx.error {
+            interpreter.dp = ctx.dp;
+            return Err(e);
+        }
+        interpreter.dp = end as usize - range.start as usize;
+        Ok(())
+    }
+}
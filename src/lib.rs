@@ -0,0 +1,53 @@
+//! Brainfuck interpreter library.
+//!
+//! A program is parsed with [`parse`], optionally optimized with [`optimize`]
+//! and then executed by an [`Interpreter`], either directly with
+//! [`Interpreter::run`] or after being lowered to flat bytecode with [`lower`]
+//! and [`Interpreter::execute`]. Tokens and instructions keep the [`Span`] of
+//! the source they come from, which the [`Debugger`] uses to step through it
+//! and a [`Profile`] uses to map execution counts back to the source. A
+//! [`Machine`] runs bytecode in slices, for callers driving it from an event
+//! loop, and a [`Session`] keeps a tape between programs, for a REPL.
+
+mod backend;
+mod bytecode;
+mod cell;
+mod config;
+mod debugger;
+mod diagnostic;
+mod error;
+mod interpreter;
+#[cfg(feature = "jit")]
+mod jit;
+mod machine;
+mod optimizer;
+mod output;
+mod parser;
+mod profiler;
+mod session;
+mod snapshot;
+mod token;
+
+pub use backend::{
+    c::emit_c,
+    elf::build_elf,
+    llvm::emit_llvm,
+    wasm::{emit_wasm, emit_wat},
+};
+pub use bytecode::{lower, Instruction, Program};
+pub use cell::Cell;
+pub use config::{
+    CellWidth, EdgePolicy, EofPolicy, Flush, Limits, Options, OutputMode, Overflow, Tape,
+};
+pub use debugger::{Debugger, Stop};
+pub use diagnostic::{Diagnostic, Hint};
+pub use error::Error;
+pub use interpreter::{Interpreter, MEMORY_SIZE};
+pub use machine::{Machine, Status};
+pub use optimizer::optimize;
+pub use output::{encoded_len, OutputSink};
+pub use parser::parse;
+pub use profiler::{LoopProfile, Profile};
+pub use session::{unclosed, Session, UNDO_LIMIT};
+pub use snapshot::SNAPSHOT_VERSION;
+pub use token::{Span, Token, TokenKind};
@@ -0,0 +1,108 @@
+use std::collections::VecDeque;
+
+use crate::{
+    bytecode::{Instruction, Program},
+    cell::Cell,
+    config::Options,
+    error::Error,
+    interpreter::Interpreter,
+    token::Span,
+};
+
+/// Why [`Machine::step`] returned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Status {
+    /// The budget of instructions was used up.
+    Paused,
+    /// The next instruction is a `,` and there is no input left, see
+    /// [`Machine::push_input`] and [`Machine::close_input`].
+    NeedsInput,
+    /// A `.` wrote these bytes.
+    Output(Vec<u8>),
+    /// There are no more instructions.
+    Halted,
+}
+
+/// Executes bytecode a few instructions at a time, keeping its position
+/// between calls, so that it can be driven by an event loop.
+///
+/// Input is given to the machine as it arrives and output is returned as it
+/// is produced, instead of going through blocking streams. The limits of the
+/// options are counted from the creation of the machine.
+pub struct Machine<C: Cell = u8> {
+    pub(crate) interpreter: Interpreter<C>,
+    pub(crate) program: Program,
+    pub(crate) pc: usize,
+    pub(crate) input: VecDeque<u8>,
+    /// No more input will be pushed, `,` applies the EOF policy.
+    pub(crate) closed: bool,
+}
+
+impl<C: Cell> Machine<C> {
+    pub fn new(program: Program, options: Options) -> Self {
+        let mut interpreter = Interpreter::new(options);
+        interpreter.start();
+        Self {
+            interpreter,
+            program,
+            pc: 0,
+            input: VecDeque::new(),
+            closed: false,
+        }
+    }
+    pub fn interpreter(&self) -> &Interpreter<C> {
+        &self.interpreter
+    }
+    /// Index of the next instruction.
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+    /// Span of the next instruction, `None` once the program halted.
+    pub fn span(&self) -> Option<Span> {
+        self.program.spans.get(self.pc).copied()
+    }
+    /// Queues bytes for the following `,`.
+    pub fn push_input(&mut self, bytes: &[u8]) {
+        self.input.extend(bytes)
+    }
+    /// Marks the end of the input, once the queued bytes are read.
+    pub fn close_input(&mut self) {
+        self.closed = true
+    }
+    /// Executes at most `budget` instructions, stopping early when the
+    /// program needs input, writes output or halts.
+    ///
+    /// After an error the machine stays on the failing instruction.
+    pub fn step(&mut self, budget: u64) -> Result<Status, Error> {
+        let mut output = Vec::new();
+        for _ in 0..budget {
+            let Some(instruction) = self.program.instructions.get(self.pc) else {
+                return Ok(Status::Halted);
+            };
+            let reads = matches!(
+                instruction,
+                Instruction::Input | Instruction::InputAt { .. }
+            );
+            if reads && self.input.is_empty() && !self.closed {
+                return Ok(Status::NeedsInput);
+            }
+            self.pc = self
+                .interpreter
+                .execute_at(
+                    &self.program.instructions,
+                    self.pc,
+                    &mut self.input,
+                    &mut output,
+                )
+                .map_err(|e| e.at(self.program.spans[self.pc]))?;
+            if !output.is_empty() {
+                return Ok(Status::Output(output));
+            }
+        }
+        Ok(if self.pc < self.program.instructions.len() {
+            Status::Paused
+        } else {
+            Status::Halted
+        })
+    }
+}
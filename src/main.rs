@@ -1,220 +1,671 @@
 use std::{
     env::{self, Args},
     fs,
-    io::{Read, Write},
-    path::Path,
+    io::{self, BufWriter, IsTerminal, StdinLock, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
 };
 
-const MEMORY_SIZE: usize = 30_000;
-
-struct Interpreter {
-    memory: Vec<u8>,
-    dp: usize, // Data Pointer
-}
-impl Interpreter {
-    fn write_memory(&mut self, byte: u8) {
-        self.memory[self.dp] = byte
-    }
-    fn read_memory(&self) -> u8 {
-        self.memory[self.dp]
-    }
-    fn run(&mut self, istructions: &Vec<Token>) {
-        for token in istructions {
-            match token {
-                Token::Output => print!("{}", self.read_memory() as char),
-                Token::Input => {
-                    self.memory[self.dp] = match read_input() {
-                        Ok(data) => data,
-                        Err(e) => {
-                            eprintln!(
-                                "\n\n[RUNTIME ERROR] error while reading data\nMessage error: {}",
-                                e
-                            );
-                            std::process::exit(1);
-                        }
-                    }
+use mindfuck::{
+    build_elf, emit_c, emit_llvm, emit_wasm, emit_wat, lower, optimize, parse, unclosed, Cell,
+    CellWidth, Debugger, Error, Flush, Interpreter, Limits, Options, Program, Session, Stop, Token,
+};
+
+const USAGE: &str = "Usage: {program} [options] <brainfuck file path>
+       {program} build [options] <brainfuck file path> [-o <executable path>]
+       {program} debug [options] <brainfuck file path>
+       {program} repl [options] [brainfuck file path]
+Options:
+    --tape <size|unbounded|bidirectional>
+    --edge <wrap|error|clamp>
+    --cell <8|16|32>
+    --overflow <wrap|saturate|trap>
+    --output <byte|utf8|decimal>
+    --eof <0|-1|unchanged>
+    --flush <input|exit>  flush the output before every `,` or only at the end, by default
+                          before `,` only when the input is a terminal
+    --emit <c|llvm|wasm|wat>
+                          print the program translated to another language instead of running it
+    --error-format <human|json>
+                          how compile errors are reported
+    --max-steps <n>       stop after executing n instructions
+    --timeout <seconds>   stop after running for this long
+    --max-output <bytes>  stop before writing more than this to the output
+    --max-tape <cells>    stop before a growing tape gets longer than this
+    --jit                 compile to native code (8 bit cells, needs the `jit` feature)
+    --profile             print the most executed code and loops after running
+    --profile-folded <path>
+                          write the execution counts as folded stacks for flamegraph tools";
+
+/// Languages a program can be translated to with `--emit`.
+enum Emit {
+    C,
+    Llvm,
+    Wasm,
+    Wat,
+}
+
+impl FromStr for Emit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "c" => Ok(Emit::C),
+            "llvm" => Ok(Emit::Llvm),
+            "wasm" => Ok(Emit::Wasm),
+            "wat" => Ok(Emit::Wat),
+            _ => Err(format!(
+                "invalid language `{s}`, expected `c`, `llvm`, `wasm` or `wat`"
+            )),
+        }
+    }
+}
+
+enum Command {
+    /// Runs the program.
+    Run,
+    /// Compiles the program to an executable.
+    Build,
+    /// Runs the program in the step debugger.
+    Debug,
+    /// Runs lines typed by the user on the same tape.
+    Repl,
+}
+
+/// How compile errors are printed.
+enum ErrorFormat {
+    Human,
+    /// One JSON object per line, for editors.
+    Json,
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(ErrorFormat::Human),
+            "json" => Ok(ErrorFormat::Json),
+            _ => Err(format!(
+                "invalid error format `{s}`, expected `human` or `json`"
+            )),
+        }
+    }
+}
+
+struct Config {
+    command: Command,
+    file_path: String,
+    output_path: Option<String>,
+    options: Options,
+    jit: bool,
+    emit: Option<Emit>,
+    error_format: ErrorFormat,
+    profile: bool,
+    folded_path: Option<String>,
+}
+
+fn usage_error(program: &str, message: &str) -> ! {
+    eprintln!("[ERROR] {message}");
+    eprintln!("[INFO] {}", USAGE.replace("{program}", program));
+    std::process::exit(1);
+}
+
+/// Parses the value of a limit, which is checked by the interpreter only.
+fn limit_value<T: FromStr>(program: &str, flag: &str, value: Option<String>) -> T {
+    let value = value.unwrap_or_else(|| usage_error(program, &format!("missing value for {flag}")));
+    value
+        .parse()
+        .unwrap_or_else(|_| usage_error(program, &format!("invalid value `{value}` for {flag}")))
+}
+
+fn option_value<T: FromStr<Err = String>>(program: &str, flag: &str, value: Option<String>) -> T {
+    let value = value.unwrap_or_else(|| usage_error(program, &format!("missing value for {flag}")));
+    value
+        .parse()
+        .unwrap_or_else(|e: String| usage_error(program, &e))
+}
+
+impl Config {
+    fn parse_args(mut args: Args) -> Self {
+        let program = args.next().unwrap();
+        let mut args = args.peekable();
+        let command = match args.peek().map(String::as_str) {
+            Some("build") => {
+                args.next();
+                Command::Build
+            }
+            Some("debug") => {
+                args.next();
+                Command::Debug
+            }
+            Some("repl") => {
+                args.next();
+                Command::Repl
+            }
+            _ => Command::Run,
+        };
+        let mut file_path = None;
+        let mut output_path = None;
+        let mut options = Options::default();
+        let mut jit = false;
+        let mut emit = None;
+        let mut error_format = ErrorFormat::Human;
+        let mut flush = None;
+        let mut profile = false;
+        let mut folded_path = None;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--tape" => options.tape = option_value(&program, &arg, args.next()),
+                "--edge" => options.edge = option_value(&program, &arg, args.next()),
+                "--cell" => options.cell = option_value(&program, &arg, args.next()),
+                "--overflow" => options.overflow = option_value(&program, &arg, args.next()),
+                "--output" => options.output = option_value(&program, &arg, args.next()),
+                "--eof" => options.eof = option_value(&program, &arg, args.next()),
+                "--flush" => flush = Some(option_value(&program, &arg, args.next())),
+                "-o" if matches!(command, Command::Build) => {
+                    let path = args.next();
+                    output_path =
+                        Some(path.unwrap_or_else(|| usage_error(&program, "missing value for -o")))
                 }
-                Token::Move(v) => match self.dp.checked_add_signed(*v) {
-                    Some(mut new_dp) => {
-                        if new_dp >= MEMORY_SIZE {
-                            // Overflow is not possible
-                            // The only way an overflow is possible is if there are enough '+'s in a row in the brainfuck file
-                            // (so it doesn't happen using loops) to cause an overflow with the usize type, but this is highly unlikely
-                            new_dp -= MEMORY_SIZE
-                        }
-                        self.dp = new_dp
-                    }
-                    None => {
-                        // Underflow
-                        let v = -v as usize;
-                        let new_dp = v - self.dp;
-                        self.dp = MEMORY_SIZE - new_dp;
-                    }
-                },
-                Token::IncValue(v) => {
-                    self.write_memory(self.read_memory().wrapping_add_signed(*v as i8))
+                "--emit" => emit = Some(option_value(&program, &arg, args.next())),
+                "--error-format" => error_format = option_value(&program, &arg, args.next()),
+                "--max-steps" => {
+                    options.limits.steps = Some(limit_value(&program, &arg, args.next()))
                 }
-                Token::Loop(istr) => {
-                    while self.read_memory() != 0 {
-                        self.run(istr)
-                    }
+                "--timeout" => {
+                    let seconds: f64 = limit_value(&program, &arg, args.next());
+                    let timeout = Duration::try_from_secs_f64(seconds).unwrap_or_else(|_| {
+                        usage_error(&program, &format!("invalid value `{seconds}` for {arg}"))
+                    });
+                    options.limits.time = Some(timeout)
+                }
+                "--max-output" => {
+                    options.limits.output = Some(limit_value(&program, &arg, args.next()))
+                }
+                "--max-tape" => {
+                    options.limits.tape = Some(limit_value(&program, &arg, args.next()))
                 }
+                "--profile" => profile = true,
+                "--profile-folded" => {
+                    let path = args.next();
+                    folded_path = Some(path.unwrap_or_else(|| {
+                        usage_error(&program, "missing value for --profile-folded")
+                    }))
+                }
+                "--jit" if cfg!(feature = "jit") => jit = true,
+                "--jit" => usage_error(&program, "compiled without the `jit` feature"),
+                _ if arg.starts_with("--") => {
+                    usage_error(&program, &format!("unknown option {arg}"))
+                }
+                _ if file_path.is_none() => file_path = Some(arg),
+                _ => usage_error(&program, "more than one brainfuck file provided"),
             }
         }
+        let file_path = match (file_path, &command) {
+            (Some(path), _) => path,
+            // The REPL can start from an empty tape
+            (None, Command::Repl) => String::new(),
+            (None, _) => usage_error(&program, "no path to the brainfuck file provided"),
+        };
+        let compiled = emit.is_some() || matches!(command, Command::Build);
+        options.flush = match flush {
+            Some(flush) => flush,
+            // Prompts must show up before waiting for an answer, piped input
+            // can't be waiting for the output
+            None if compiled || io::stdin().is_terminal() => Flush::BeforeInput,
+            None => Flush::AtExit,
+        };
+        if compiled && options.limits != Limits::default() {
+            usage_error(
+                &program,
+                "limits are only enforced when running the program",
+            )
+        }
+        if jit && options.cell != CellWidth::U8 {
+            usage_error(&program, "--jit only supports 8 bit cells")
+        }
+        Self {
+            command,
+            file_path,
+            output_path,
+            options,
+            jit,
+            emit,
+            error_format,
+            profile,
+            folded_path,
+        }
     }
 }
 
-impl Default for Interpreter {
-    fn default() -> Self {
-        Self {
-            memory: vec![0; MEMORY_SIZE],
-            dp: 0,
+/// Capacity of the output buffer when the output is not a terminal.
+const OUTPUT_BUFFER: usize = 64 * 1024;
+
+/// Calls `f` with the standard input and output, buffering the output unless
+/// it is a terminal. The output is flushed afterwards, also after an error.
+fn with_stdio<T>(
+    f: impl FnOnce(&mut StdinLock<'static>, &mut Box<dyn Write>) -> Result<T, Error>,
+) -> Result<T, Error> {
+    let stdout = io::stdout();
+    let mut output: Box<dyn Write> = if stdout.is_terminal() {
+        Box::new(stdout.lock())
+    } else {
+        Box::new(BufWriter::with_capacity(OUTPUT_BUFFER, stdout.lock()))
+    };
+    let result = f(&mut io::stdin().lock(), &mut output);
+    let flushed = output.flush();
+    let value = result?;
+    flushed?;
+    Ok(value)
+}
+
+fn run<C: Cell>(options: Options, program: &Program) -> Result<(), Error> {
+    with_stdio(|input, output| Interpreter::<C>::new(options).execute(program, input, output))
+}
+
+/// Runs the program counting the executed instructions, then reports them.
+fn profile<C: Cell>(config: &Config, source: &str, program: &Program) -> Result<(), Error> {
+    // The counts up to an error or a limit are reported too
+    let mut result = Ok(());
+    let profile = with_stdio(|input, output| {
+        let (profile, ran) = Interpreter::<C>::new(config.options).profile(program, input, output);
+        result = ran;
+        Ok(profile)
+    })?;
+    if config.profile {
+        eprintln!("\n[PROFILE] {}", profile.report(source));
+    }
+    if let Some(path) = &config.folded_path {
+        let root = Path::new(&config.file_path)
+            .file_name()
+            .map_or(config.file_path.clone(), |name| {
+                name.to_string_lossy().into_owned()
+            });
+        fs::write(path, profile.folded(source, &root))?;
+    }
+    result
+}
+
+fn run_jit(options: Options, tokens: &[Token]) -> Result<(), Error> {
+    #[cfg(feature = "jit")]
+    return with_stdio(|input, output| {
+        Interpreter::<u8>::new(options).run_jit(tokens, input, output)
+    });
+    #[cfg(not(feature = "jit"))]
+    run::<u8>(options, &lower(tokens))
+}
+
+fn build(config: &Config, tokens: &[Token]) {
+    let executable = build_elf(tokens, &config.options).unwrap_or_else(|e| {
+        eprintln!("[ERROR] {}", e);
+        std::process::exit(1)
+    });
+    let output_path = match &config.output_path {
+        Some(path) => PathBuf::from(path),
+        None => Path::new(&config.file_path).with_extension(""),
+    };
+    if let Err(e) = fs::write(&output_path, executable) {
+        eprintln!(
+            "[ERROR] the executable can't be written\nError message: {}",
+            e
+        );
+        std::process::exit(1)
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if let Err(e) = fs::set_permissions(&output_path, fs::Permissions::from_mode(0o755)) {
+            eprintln!(
+                "[ERROR] the executable can't be made executable\nError message: {}",
+                e
+            );
+            std::process::exit(1)
         }
     }
 }
 
-fn read_input() -> Result<u8, std::io::Error> {
-    let mut input = [0];
-    std::io::stdout().flush()?;
-    std::io::stdin().read(&mut input)?;
-    Ok(input[0])
+const DEBUG_HELP: &str = "Commands:
+    step [n]              s  execute the next n instructions
+    next                  n  execute the next instruction, or the whole loop it starts
+    continue              c  run until a breakpoint, a watchpoint or the end
+    break <line>:<col>    b  add a breakpoint, every `#` in the source is one too
+    delete <line>:<col>   d  remove a breakpoint
+    watch <cell> [value]  w  stop when the cell changes, or when it becomes the value
+    unwatch <cell>           remove a watchpoint
+    tape [radius]         t  show the cells around the data pointer
+    help                  h
+    quit                  q
+The program reads its input from the terminal too.";
+
+/// Byte offset of `line:column` in the source.
+fn source_offset(source: &str, location: &str) -> Option<usize> {
+    let (line, column) = location.split_once(':')?;
+    let (line, column): (usize, usize) = (line.parse().ok()?, column.parse().ok()?);
+    let start: usize = source
+        .split_inclusive('\n')
+        .take(line.checked_sub(1)?)
+        .map(str::len)
+        .sum();
+    Some(start + column.checked_sub(1)?)
+}
+
+fn print_tape<C: Cell>(interpreter: &Interpreter<C>, radius: isize) {
+    let dp = interpreter.dp();
+    let cells: Vec<String> = (dp - radius..=dp + radius)
+        .filter_map(|position| {
+            let value = interpreter.cell(position)?.to_u32();
+            Some(if position == dp {
+                format!("[{position}: {value}]")
+            } else {
+                format!("{position}: {value}")
+            })
+        })
+        .collect();
+    println!("{}", cells.join("  "));
 }
 
-#[derive(Debug, Clone, PartialEq)]
-enum Token {
-    Output,
-    Input,
-    Move(isize),
-    IncValue(isize),
-    Loop(Vec<Token>),
+/// Prints why the debugger stopped and where.
+fn report<C: Cell>(debugger: &Debugger<C>, stop: Stop) {
+    match stop {
+        Stop::Finished => return println!("The program finished."),
+        Stop::Watchpoint { position } => println!("Watchpoint on cell {position}."),
+        Stop::Breakpoint => println!("Breakpoint."),
+        Stop::Step => (),
+    }
+    if let Some(span) = debugger.span() {
+        let source = debugger.source();
+        let (line, column) = span.line_column(source);
+        println!("At {line}:{column}:\n{}", span.snippet(source));
+    }
 }
 
-fn search_extra_loop(stack: &mut Vec<(char, u32, u32)>) -> (u32, u32) {
-    let mut level: u32 = 0;
-    loop {
-        if let Some(values) = stack.pop() {
-            match values {
-                ('[', line, position) => {
-                    if level == 0 {
-                        return (line, position);
-                    }
-                    level -= 1
-                }
-                (']', _, _) => level += 1,
-                _ => panic!("token unespected in the stack: {:?}", values.0),
+fn compile_error(config: &Config, source: &str, error: Error) -> ! {
+    match (&config.error_format, error) {
+        (ErrorFormat::Human, Error::Syntax(diagnostics)) => {
+            for diagnostic in diagnostics {
+                eprintln!(
+                    "[COMPILE ERROR] {}\n",
+                    diagnostic.render(source, &config.file_path)
+                );
             }
-        } else {
-            panic!("The extra loop was not found")
         }
+        (ErrorFormat::Json, Error::Syntax(diagnostics)) => {
+            for diagnostic in diagnostics {
+                eprintln!("{}", diagnostic.to_json(source, &config.file_path));
+            }
+        }
+        (_, e) => eprintln!("[COMPILE ERROR] {}", e),
     }
+    std::process::exit(1)
 }
 
-fn compile<'a>(
-    mut bytes: std::slice::Iter<'a, u8>,
-    level: usize,
-    stack: &mut Vec<(char, u32, u32)>,
-    line: &mut u32,
-    position: &mut u32,
-) -> (Vec<Token>, std::slice::Iter<'a, u8>) {
-    let mut tokens: Vec<Token> = Vec::new();
+fn debug<C: Cell>(config: &Config, source: &str) {
+    let mut debugger = Debugger::<C>::new(source, config.options)
+        .unwrap_or_else(|e| compile_error(config, source, e));
+    let markers = debugger.break_at_markers();
+    if markers > 0 {
+        println!("Breakpoints at `#` markers: {markers}.");
+    }
+    println!("Type `help` for the commands.");
 
+    report(&debugger, Stop::Step);
     loop {
-        if let Some(byte) = bytes.next() {
-            let char = *byte as char;
-            match char {
-                '\n' => {
-                    *line += 1;
-                    *position = 0
+        print!("(debug) ");
+        io::stdout().flush().ok();
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line).unwrap_or(0) == 0 {
+            return;
+        }
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or("step");
+        let argument = words.next();
+        let (input, output) = (&mut io::stdin(), &mut io::stdout());
+        let result = match command {
+            "step" | "s" => {
+                let count: u32 = argument.and_then(|n| n.parse().ok()).unwrap_or(1);
+                let mut result = Ok(Stop::Step);
+                for _ in 0..count {
+                    result = debugger.step(input, output);
+                    if result.as_ref().map_or(true, |stop| *stop != Stop::Step) {
+                        break;
+                    }
                 }
-                '>' => tokens.push(Token::Move(1)),
-                '<' => tokens.push(Token::Move(-1)),
-                '+' => tokens.push(Token::IncValue(1)),
-                '-' => tokens.push(Token::IncValue(-1)),
-                '.' => tokens.push(Token::Output),
-                ',' => tokens.push(Token::Input),
-                '[' => {
-                    stack.push(('[', *line, *position));
-                    let (inner_tokens, next_bytes) =
-                        compile(bytes, level + 1, stack, line, position);
-                    tokens.push(Token::Loop(inner_tokens));
-                    bytes = next_bytes
+                result
+            }
+            "next" | "n" => debugger.step_over(input, output),
+            "continue" | "c" => debugger.resume(input, output),
+            "break" | "b" | "delete" | "d" => {
+                let offset = argument.and_then(|location| source_offset(source, location));
+                match (offset, command) {
+                    (None, _) => println!("Expected a location as <line>:<column>."),
+                    (Some(offset), "break" | "b") => match debugger.add_breakpoint(offset) {
+                        Some(span) => {
+                            let (line, column) = span.line_column(source);
+                            println!("Breakpoint at {line}:{column}.")
+                        }
+                        None => println!("There are no instructions after that location."),
+                    },
+                    (Some(offset), _) => {
+                        if !debugger.remove_breakpoint(offset) {
+                            println!("There is no breakpoint there.")
+                        }
+                    }
                 }
-                ']' => {
-                    if level == 0 {
-                        eprintln!(
-                            "[COMPILE ERROR] closed an nonexistent loop on line: {}:{}",
-                            line, position
-                        );
-                        std::process::exit(1);
-                    } else {
-                        stack.push((']', *line, *position));
-                        return (tokens, bytes);
+                continue;
+            }
+            "watch" | "w" | "unwatch" => {
+                match argument.and_then(|position| position.parse().ok()) {
+                    None => println!("Expected the position of a cell."),
+                    Some(position) if command == "unwatch" => {
+                        if !debugger.unwatch(position) {
+                            println!("There is no watchpoint on that cell.")
+                        }
+                    }
+                    Some(position) => {
+                        let value = words.next().and_then(|value| value.parse().ok());
+                        debugger.watch(position, value)
                     }
                 }
-                _ => (),
+                continue;
             }
-        } else {
-            if level > 0 {
-                let (line, position) = search_extra_loop(stack);
-                eprintln!(
-                    "[COMPILE ERROR] opened an nonexistent loop on line: {}:{}",
-                    line, position
+            "tape" | "t" => {
+                print_tape(
+                    debugger.interpreter(),
+                    argument.and_then(|r| r.parse().ok()).unwrap_or(5),
                 );
-                std::process::exit(1);
+                continue;
+            }
+            "quit" | "q" => return,
+            _ => {
+                println!("{DEBUG_HELP}");
+                continue;
+            }
+        };
+        output.flush().ok();
+        match result {
+            Ok(stop) => report(&debugger, stop),
+            Err(e) => {
+                // The state stays as it was before the failing instruction
+                println!("\n[RUNTIME ERROR] {}", e);
+                report(&debugger, Stop::Step)
             }
-            break;
         }
-        *position += 1
     }
+}
 
-    (tokens, bytes)
+const REPL_HELP: &str =
+    "Every line is run on the same tape, a line with an unclosed `[` continues on
+the next one. Commands:
+    :tape [radius]        :t  show the cells around the data pointer
+    :set <cell> <value>       change a cell
+    :reset                    clear the tape
+    :load <file>          :l  run a file on the tape
+    :undo                 :u  restore the tape from before the last change
+    :help                 :h
+    :quit                 :q
+The programs read their input from the terminal too.";
+
+/// Passes the output through, remembering its last byte.
+struct Tracked<W> {
+    inner: W,
+    last: Option<u8>,
 }
 
-fn optimize(tokens: Vec<Token>) -> Vec<Token> {
-    let mut optimized: Vec<Token> = Vec::with_capacity(tokens.len());
+impl<W: Write> Write for Tracked<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        if written > 0 {
+            self.last = Some(buf[written - 1]);
+        }
+        Ok(written)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
 
-    for token in tokens {
-        match (optimized.last(), token) {
-            (_, Token::Loop(sub_exp)) => {
-                optimized.push(Token::Loop(optimize(sub_exp)));
+/// Compiles and runs `source` on the tape, then shows the tape.
+fn run_line<C: Cell>(session: &mut Session<C>, source: &str, path: &str, options: &Options) {
+    let tokens = match parse(source) {
+        Ok(tokens) => optimize(tokens, options),
+        Err(Error::Syntax(diagnostics)) => {
+            for diagnostic in diagnostics {
+                println!("[COMPILE ERROR] {}\n", diagnostic.render(source, path));
             }
+            return;
+        }
+        Err(e) => return println!("[COMPILE ERROR] {}", e),
+    };
+    if tokens.is_empty() {
+        return;
+    }
+    let mut output = Tracked {
+        inner: io::stdout(),
+        last: None,
+    };
+    let result = session.run(&lower(&tokens), &mut io::stdin(), &mut output);
+    output.flush().ok();
+    if !matches!(output.last, None | Some(b'\n')) {
+        println!();
+    }
+    if let Err(e) = result {
+        // The cells changed before the error are kept, `:undo` reverts them
+        println!("[RUNTIME ERROR] {}", e);
+        if let Some(span) = e.span() {
+            let (line, column) = span.line_column(source);
+            println!(" --> {path}:{line}:{column}\n{}", span.snippet(source));
+        }
+    }
+    print_tape(session.interpreter(), 5);
+}
 
-            (Some(&Token::IncValue(n)), Token::IncValue(v)) => {
-                optimized.pop();
-                optimized.push(Token::IncValue(n + v));
-            }
+fn load<C: Cell>(session: &mut Session<C>, path: &str, options: &Options) {
+    match fs::read_to_string(path) {
+        Ok(source) => run_line(session, &source, path, options),
+        Err(e) => println!("[ERROR] the file can't be read\nError message: {}", e),
+    }
+}
 
-            (Some(&Token::Move(n)), Token::Move(v)) => {
-                optimized.pop();
-                optimized.push(Token::Move(n + v))
+/// Runs a `:` command, returns false to quit.
+fn run_command<C: Cell>(session: &mut Session<C>, line: &str, options: &Options) -> bool {
+    let mut words = line.split_whitespace();
+    let command = words.next().unwrap_or("");
+    let argument = words.next();
+    match command {
+        "tape" | "t" => print_tape(
+            session.interpreter(),
+            argument.and_then(|r| r.parse().ok()).unwrap_or(5),
+        ),
+        "set" => {
+            let position: Option<isize> = argument.and_then(|p| p.parse().ok());
+            let value = words.next().and_then(|v| v.parse().ok());
+            match (position, value.map(C::from_u32)) {
+                (Some(position), Some(Some(value))) => match session.set_cell(position, value) {
+                    Ok(()) => print_tape(session.interpreter(), 5),
+                    Err(e) => println!("[ERROR] {}", e),
+                },
+                (Some(_), Some(None)) => println!("The value does not fit in a cell."),
+                _ => println!("Expected the position of a cell and a value."),
+            }
+        }
+        "reset" => session.reset(),
+        "load" | "l" => match argument {
+            Some(path) => load(session, path, options),
+            None => println!("Expected the path of a brainfuck file."),
+        },
+        "undo" | "u" => {
+            if session.undo() {
+                print_tape(session.interpreter(), 5)
+            } else {
+                println!("There is nothing to undo.")
             }
-
-            (_, e) => optimized.push(e.clone()),
         }
+        "quit" | "q" => return false,
+        _ => println!("{REPL_HELP}"),
     }
-    optimized
+    true
 }
 
-struct Config {
-    file_path: String,
-}
+fn repl<C: Cell>(config: &Config) {
+    let options = &config.options;
+    let mut session = Session::<C>::new(config.options);
+    if !config.file_path.is_empty() {
+        load(&mut session, &config.file_path, options);
+    }
+    println!("Type `:help` for the commands.");
 
-impl Config {
-    fn parse_args(mut args: Args) -> Self {
-        let program = args.next().unwrap();
-        let file_path = args.next().unwrap_or_else(|| {
-            eprintln!("[ERROR] no path to the brainfuck file provided");
-            eprintln!("[INFO] Usage: {program} <brainfuck file path>");
-            std::process::exit(1);
-        });
-        Self {
-            file_path: file_path,
+    // Lines of a loop not closed yet
+    let mut pending = String::new();
+    loop {
+        print!("{}", if pending.is_empty() { "> " } else { ". " });
+        io::stdout().flush().ok();
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line).unwrap_or(0) == 0 {
+            return;
+        }
+        if pending.is_empty() {
+            if let Some(command) = line.trim_start().strip_prefix(':') {
+                if !run_command(&mut session, command, options) {
+                    return;
+                }
+                continue;
+            }
+        }
+        pending.push_str(&line);
+        if !unclosed(&pending) {
+            run_line(
+                &mut session,
+                &std::mem::take(&mut pending),
+                "<repl>",
+                options,
+            );
         }
     }
 }
 
+/// Exit code of a runtime error, every exceeded limit has its own.
+fn exit_code(error: &Error) -> i32 {
+    match error.inner() {
+        Error::StepLimitExceeded { .. } => 3,
+        Error::TimeLimitExceeded { .. } => 4,
+        Error::OutputLimitExceeded { .. } => 5,
+        Error::TapeLimitExceeded { .. } => 6,
+        _ => 1,
+    }
+}
+
 fn main() {
     let config = Config::parse_args(env::args());
+    if let Command::Repl = config.command {
+        match config.options.cell {
+            CellWidth::U8 => repl::<u8>(&config),
+            CellWidth::U16 => repl::<u16>(&config),
+            CellWidth::U32 => repl::<u32>(&config),
+        }
+        return;
+    }
     let path = Path::new(config.file_path.as_str());
     if !path.exists() {
         eprintln!("[ERROR] the path does not exists");
@@ -229,9 +680,58 @@ fn main() {
         std::process::exit(1)
     });
 
-    let mut interpreter = Interpreter::default();
+    if let Command::Debug = config.command {
+        match config.options.cell {
+            CellWidth::U8 => debug::<u8>(&config, &data),
+            CellWidth::U16 => debug::<u16>(&config, &data),
+            CellWidth::U32 => debug::<u32>(&config, &data),
+        }
+        return;
+    }
+
+    let tokens = optimize(
+        parse(&data).unwrap_or_else(|e| compile_error(&config, &data, e)),
+        &config.options,
+    );
+
+    if let Command::Build = config.command {
+        build(&config, &tokens);
+        return;
+    }
 
-    let tokens = optimize(compile(data.as_bytes().iter(), 0, &mut Vec::new(), &mut 1, &mut 1).0);
+    if let Some(emit) = config.emit {
+        let code = match emit {
+            Emit::C => Ok(emit_c(&tokens, &config.options).into_bytes()),
+            Emit::Llvm => emit_llvm(&tokens, &config.options).map(String::into_bytes),
+            Emit::Wasm => emit_wasm(&tokens, &config.options),
+            Emit::Wat => emit_wat(&tokens, &config.options).map(String::into_bytes),
+        };
+        let written = code.and_then(|code| Ok(io::stdout().write_all(&code)?));
+        if let Err(e) = written {
+            eprintln!("[ERROR] {}", e);
+            std::process::exit(1)
+        }
+        return;
+    }
 
-    interpreter.run(&tokens)
+    let profiling = config.profile || config.folded_path.is_some();
+    let result = match config.options.cell {
+        // Profiling counts the instructions of the interpreter, even with `--jit`
+        CellWidth::U8 if profiling => profile::<u8>(&config, &data, &lower(&tokens)),
+        CellWidth::U16 if profiling => profile::<u16>(&config, &data, &lower(&tokens)),
+        CellWidth::U32 if profiling => profile::<u32>(&config, &data, &lower(&tokens)),
+        CellWidth::U8 if config.jit => run_jit(config.options, &tokens),
+        CellWidth::U8 => run::<u8>(config.options, &lower(&tokens)),
+        CellWidth::U16 => run::<u16>(config.options, &lower(&tokens)),
+        CellWidth::U32 => run::<u32>(config.options, &lower(&tokens)),
+    };
+    if let Err(e) = result {
+        eprintln!("\n\n[RUNTIME ERROR] {}", e);
+        if let Some(span) = e.span() {
+            let (line, column) = span.line_column(&data);
+            eprintln!(" --> {}:{line}:{column}", config.file_path);
+            eprintln!("{}", span.snippet(&data));
+        }
+        std::process::exit(exit_code(&e))
+    }
 }
@@ -0,0 +1,230 @@
+use std::{collections::BTreeMap, mem, vec};
+
+use crate::{
+    config::{EdgePolicy, Options, Overflow, Tape},
+    token::{Span, Token, TokenKind},
+};
+
+/// Whether two increments of the same cell can be merged into one. A cell that
+/// saturates or traps can overflow on the way even when the sum is in range,
+/// so then only increments in the same direction are.
+fn mergeable(options: &Options, a: isize, b: isize) -> bool {
+    options.overflow == Overflow::Wrap || a.signum() * b.signum() >= 0
+}
+
+/// Whether the data pointer has to go through every cell the moves of the
+/// program go through: an edge or a tape limit can stop or clamp it on the
+/// way, even if it comes back. Then only moves in the same direction are
+/// merged and none is folded into an offset.
+fn exact_moves(options: &Options) -> bool {
+    match options.tape {
+        Tape::Fixed(_) => options.edge != EdgePolicy::Wrap,
+        Tape::Unbounded => true,
+        Tape::Bidirectional => options.limits.tape.is_some(),
+    }
+}
+
+/// Recognizes `[-]`, `[+]`, scan loops like `[>]` and balanced multiply loops
+/// like `[->+>++<<]`, returns the tokens replacing the loop. Only scans are
+/// recognized when cells don't wrap, and multiply loops need inexact moves.
+fn idiom(body: &[Token], options: &Options) -> Option<Vec<TokenKind>> {
+    match body.iter().map(|token| &token.kind).collect::<Vec<_>>()[..] {
+        [&TokenKind::Move(step)] if step != 0 => return Some(vec![TokenKind::Scan(step)]),
+        _ if options.overflow != Overflow::Wrap => return None,
+        [TokenKind::IncValue(1 | -1)] => return Some(vec![TokenKind::SetZero]),
+        _ => (),
+    }
+
+    let mut offset: isize = 0;
+    let mut deltas: BTreeMap<isize, isize> = BTreeMap::new();
+    for token in body {
+        match token.kind {
+            TokenKind::Move(v) => offset += v,
+            TokenKind::IncValue(v) => *deltas.entry(offset).or_insert(0) += v,
+            _ => return None,
+        }
+    }
+    // Only a counter decremented by one runs a number of times independent of the cell width
+    if exact_moves(options) || offset != 0 || deltas.remove(&0) != Some(-1) {
+        return None;
+    }
+    let mut tokens: Vec<TokenKind> = deltas
+        .into_iter()
+        .filter(|(_, factor)| *factor != 0)
+        .map(|(offset, factor)| TokenKind::MulAdd { offset, factor })
+        .collect();
+    tokens.push(TokenKind::SetZero);
+    Some(tokens)
+}
+
+/// The loops around the body being rewritten by a pass over a token tree: the
+/// rest of their tokens, what they were rewritten to so far and their span.
+/// Passes keep it instead of recursing, so that deep nesting doesn't overflow
+/// the stack.
+type Outer = Vec<(vec::IntoIter<Token>, Vec<Token>, Span)>;
+
+/// Turns the straight-line code between loops into operations addressed by
+/// offset, so that each block moves the data pointer at most once, at its end.
+fn fold_offsets(tokens: Vec<Token>, options: &Options) -> Vec<Token> {
+    let mut outer: Outer = Vec::new();
+    let mut rest = tokens.into_iter();
+    let mut folded: Vec<Token> = Vec::with_capacity(rest.len());
+    let mut offset: isize = 0;
+    // Covers the moves not emitted yet
+    let mut moves: Option<Span> = None;
+
+    loop {
+        let Some(token) = rest.next() else {
+            // Bodies end on the real data pointer, like the program
+            if let Some(moves) = moves.take() {
+                if offset != 0 {
+                    folded.push(Token::new(TokenKind::Move(offset), moves));
+                    offset = 0;
+                }
+            }
+            let Some((outer_rest, outer_folded, span)) = outer.pop() else {
+                return folded;
+            };
+            rest = outer_rest;
+            let body = mem::replace(&mut folded, outer_folded);
+            folded.push(Token::new(TokenKind::Loop(body), span));
+            continue;
+        };
+        let (kind, span) = token.into_parts();
+        match (folded.last_mut(), kind) {
+            (_, TokenKind::Move(v)) if !exact_moves(options) => {
+                offset += v;
+                moves = Some(moves.map_or(span, |moves| moves.to(span)));
+            }
+
+            (
+                Some(Token {
+                    kind: TokenKind::Add { offset: o, value } | TokenKind::Set { offset: o, value },
+                    span: previous,
+                }),
+                TokenKind::IncValue(v),
+            ) if *o == offset && mergeable(options, *value, v) => {
+                *value += v;
+                *previous = previous.to(span);
+            }
+            (_, TokenKind::IncValue(v)) => {
+                folded.push(Token::new(TokenKind::Add { offset, value: v }, span))
+            }
+
+            (
+                Some(Token {
+                    kind: TokenKind::Add { offset: o, .. } | TokenKind::Set { offset: o, .. },
+                    span: previous,
+                }),
+                TokenKind::SetZero,
+            ) if *o == offset => {
+                // The previous operation on this cell is overwritten
+                let span = previous.to(span);
+                folded.pop();
+                folded.push(Token::new(TokenKind::Set { offset, value: 0 }, span))
+            }
+            (_, TokenKind::SetZero) => {
+                folded.push(Token::new(TokenKind::Set { offset, value: 0 }, span))
+            }
+
+            (_, TokenKind::Output) => folded.push(Token::new(TokenKind::OutputAt { offset }, span)),
+            (_, TokenKind::Input) => folded.push(Token::new(TokenKind::InputAt { offset }, span)),
+
+            (_, e) => {
+                // Loops, scans and multiplications start from the real data pointer
+                if let Some(moves) = moves.take() {
+                    if offset != 0 {
+                        folded.push(Token::new(TokenKind::Move(offset), moves));
+                        offset = 0;
+                    }
+                }
+                match e {
+                    TokenKind::Loop(sub_exp) => {
+                        let sub_exp = sub_exp.into_iter();
+                        let sub_folded = Vec::with_capacity(sub_exp.len());
+                        outer.push((
+                            mem::replace(&mut rest, sub_exp),
+                            mem::replace(&mut folded, sub_folded),
+                            span,
+                        ));
+                    }
+                    e => folded.push(Token::new(e, span)),
+                }
+            }
+        }
+    }
+}
+
+fn merge(tokens: Vec<Token>, options: &Options) -> Vec<Token> {
+    let mut outer: Outer = Vec::new();
+    let mut rest = tokens.into_iter();
+    let mut optimized: Vec<Token> = Vec::with_capacity(rest.len());
+
+    loop {
+        let Some(token) = rest.next() else {
+            let Some((outer_rest, outer_optimized, span)) = outer.pop() else {
+                return optimized;
+            };
+            // The body of a loop is merged, the loop can be replaced
+            rest = outer_rest;
+            let sub_exp = mem::replace(&mut optimized, outer_optimized);
+            match idiom(&sub_exp, options) {
+                Some(tokens) => optimized.extend(
+                    // Every replacement comes from the whole loop
+                    tokens.into_iter().map(|kind| Token::new(kind, span)),
+                ),
+                None => optimized.push(Token::new(TokenKind::Loop(sub_exp), span)),
+            }
+            continue;
+        };
+        let (kind, span) = token.into_parts();
+        match (optimized.last_mut(), kind) {
+            (_, TokenKind::Loop(sub_exp)) => {
+                let sub_exp = sub_exp.into_iter();
+                let sub_optimized = Vec::with_capacity(sub_exp.len());
+                outer.push((
+                    mem::replace(&mut rest, sub_exp),
+                    mem::replace(&mut optimized, sub_optimized),
+                    span,
+                ));
+            }
+
+            (
+                Some(Token {
+                    kind: TokenKind::IncValue(n),
+                    span: previous,
+                }),
+                TokenKind::IncValue(v),
+            ) if mergeable(options, *n, v) => {
+                *n += v;
+                *previous = previous.to(span);
+            }
+            (
+                Some(Token {
+                    kind: TokenKind::Move(n),
+                    span: previous,
+                }),
+                TokenKind::Move(v),
+            ) if !exact_moves(options) || n.signum() * v.signum() >= 0 => {
+                *n += v;
+                *previous = previous.to(span);
+            }
+
+            (_, e) => optimized.push(Token::new(e, span)),
+        }
+    }
+}
+
+/// Merges consecutive `IncValue` and `Move` tokens and replaces common loop
+/// idioms with dedicated tokens, then folds moves into offset-addressed
+/// operations. Every resulting token spans all the tokens it replaces.
+///
+/// The result behaves like the original program under `options`. When cells
+/// saturate or trap, only increments in the same direction are merged and
+/// scans are the only loops rewritten. When an edge or a tape limit can stop or
+/// clamp the data pointer, only moves in the same direction are merged and
+/// none is folded into an offset. A runtime error may then point to a merged
+/// token rather than to the single command that caused it.
+pub fn optimize(tokens: Vec<Token>, options: &Options) -> Vec<Token> {
+    fold_offsets(merge(tokens, options), options)
+}
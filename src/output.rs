@@ -0,0 +1,61 @@
+use std::io::{self, Write};
+
+use crate::{config::OutputMode, error::Error};
+
+/// Longest encoding of a cell: ten digits and a newline.
+const ENCODED_MAX: usize = 11;
+
+/// Encodes `value` into `buffer`, returns the bytes used.
+fn encode(mode: OutputMode, value: u32, buffer: &mut [u8; ENCODED_MAX]) -> &[u8] {
+    match mode {
+        OutputMode::Byte => {
+            buffer[0] = value as u8;
+            &buffer[..1]
+        }
+        OutputMode::Utf8 => char::from_u32(value)
+            .unwrap_or(char::REPLACEMENT_CHARACTER)
+            .encode_utf8(buffer)
+            .as_bytes(),
+        OutputMode::Decimal => {
+            let unused = {
+                let mut cursor = &mut buffer[..];
+                writeln!(cursor, "{value}").unwrap();
+                cursor.len()
+            };
+            &buffer[..ENCODED_MAX - unused]
+        }
+    }
+}
+
+/// Number of bytes `value` is written as in `mode`.
+pub fn encoded_len(mode: OutputMode, value: u32) -> usize {
+    encode(mode, value, &mut [0; ENCODED_MAX]).len()
+}
+
+/// Writes the cells printed by `.` to a byte stream, encoded according to an
+/// [`OutputMode`].
+///
+/// Useful to hosts of the WebAssembly backend, which pass the raw cell values
+/// to `env.output`.
+pub struct OutputSink<W> {
+    mode: OutputMode,
+    writer: W,
+}
+
+impl<W: Write> OutputSink<W> {
+    pub fn new(mode: OutputMode, writer: W) -> Self {
+        Self { mode, writer }
+    }
+    pub fn write_cell(&mut self, value: u32) -> Result<(), Error> {
+        let mut buffer = [0; ENCODED_MAX];
+        self.writer
+            .write_all(encode(self.mode, value, &mut buffer))?;
+        Ok(())
+    }
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
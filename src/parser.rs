@@ -0,0 +1,171 @@
+use std::mem;
+
+use crate::{
+    diagnostic::{Diagnostic, Hint},
+    error::Error,
+    token::{Span, Token, TokenKind},
+};
+
+/// Positions of the brackets seen while compiling.
+#[derive(Default)]
+struct Brackets {
+    /// `[` and `]` offsets of every loop.
+    pairs: Vec<(usize, usize)>,
+    unmatched_open: Vec<usize>,
+    unmatched_close: Vec<usize>,
+}
+
+/// Compiles the whole program, keeping the bodies of the open loops on a
+/// stack instead of recursing, so that the nesting depth is only limited by
+/// memory.
+fn compile(source: &[u8], brackets: &mut Brackets) -> Vec<Token> {
+    let mut tokens: Vec<Token> = Vec::new();
+    // The offset of the `[` of every open loop and the tokens before it
+    let mut open: Vec<(usize, Vec<Token>)> = Vec::new();
+
+    for (start, byte) in source.iter().enumerate() {
+        let span = Span::new(start, start + 1);
+        match byte {
+            b'>' => tokens.push(Token::new(TokenKind::Move(1), span)),
+            b'<' => tokens.push(Token::new(TokenKind::Move(-1), span)),
+            b'+' => tokens.push(Token::new(TokenKind::IncValue(1), span)),
+            b'-' => tokens.push(Token::new(TokenKind::IncValue(-1), span)),
+            b'.' => tokens.push(Token::new(TokenKind::Output, span)),
+            b',' => tokens.push(Token::new(TokenKind::Input, span)),
+            b'[' => open.push((start, mem::take(&mut tokens))),
+            b']' => match open.pop() {
+                Some((open, outer)) => {
+                    brackets.pairs.push((open, start));
+                    let body = mem::replace(&mut tokens, outer);
+                    // The loop spans from `[` to the matching `]`
+                    let span = Span::new(open, start + 1);
+                    tokens.push(Token::new(TokenKind::Loop(body), span));
+                }
+                // Skipped, so that the following brackets are still checked
+                None => brackets.unmatched_close.push(start),
+            },
+            _ => (),
+        }
+    }
+    // Unclosed loops span up to the end of the source
+    while let Some((open, outer)) = open.pop() {
+        brackets.unmatched_open.push(open);
+        let body = mem::replace(&mut tokens, outer);
+        let span = Span::new(open, source.len());
+        tokens.push(Token::new(TokenKind::Loop(body), span));
+    }
+    tokens
+}
+
+/// Offsets where the lines of the source start, to find the line and column
+/// of many offsets without scanning the source again.
+struct Lines(Vec<usize>);
+
+impl Lines {
+    fn new(source: &str) -> Self {
+        let newlines = source
+            .bytes()
+            .enumerate()
+            .filter(|&(_, byte)| byte == b'\n');
+        Self(
+            std::iter::once(0)
+                .chain(newlines.map(|(i, _)| i + 1))
+                .collect(),
+        )
+    }
+    /// Line and column, both starting from 1, like [`Span::line_column`].
+    fn line_column(&self, offset: usize) -> (u32, u32) {
+        let line = self.0.partition_point(|&start| start <= offset);
+        (line as u32, (offset - self.0[line - 1] + 1) as u32)
+    }
+    fn location(&self, offset: usize) -> String {
+        let (line, column) = self.line_column(offset);
+        format!("{line}:{column}")
+    }
+    fn diagnostic(&self, offset: usize, message: &str, label: &str, hint: Hint) -> Diagnostic {
+        let (line, column) = self.line_column(offset);
+        Diagnostic {
+            message: message.to_string(),
+            span: Span::new(offset, offset + 1),
+            line,
+            column,
+            label: label.to_string(),
+            hint: Some(hint),
+        }
+    }
+}
+
+/// Explains every bracket mismatch, pointing at the bracket that most likely
+/// was meant to match.
+fn diagnostics(source: &str, brackets: &Brackets) -> Vec<Diagnostic> {
+    let lines = Lines::new(source);
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    // The pairs are sorted by their `]`, as they are closed in order
+    let last = brackets.pairs.last();
+    for &open in &brackets.unmatched_open {
+        // Every `]` after an unclosed `[` closes a loop opened after it
+        let hint = match last {
+            Some(&(inner, close)) if close > open => Hint {
+                message: format!(
+                    "the last `]`, at {}, closes the `[` at {} instead",
+                    lines.location(close),
+                    lines.location(inner)
+                ),
+                span: Some(Span::new(close, close + 1)),
+            },
+            _ => Hint {
+                message: "add a `]` after the end of the loop".to_string(),
+                span: None,
+            },
+        };
+        diagnostics.push(lines.diagnostic(
+            open,
+            "unmatched `[`",
+            "this loop is never closed",
+            hint,
+        ));
+    }
+    // The unmatched `]` are sorted too, so the pair closed right before each
+    // of them is found in a single pass
+    let mut closed = 0;
+    for &close in &brackets.unmatched_close {
+        while brackets
+            .pairs
+            .get(closed)
+            .is_some_and(|&(_, end)| end < close)
+        {
+            closed += 1;
+        }
+        let hint = match closed.checked_sub(1).map(|i| brackets.pairs[i]) {
+            Some((open, end)) => Hint {
+                message: format!(
+                    "the `[` at {} is already closed at {}",
+                    lines.location(open),
+                    lines.location(end)
+                ),
+                span: Some(Span::new(open, open + 1)),
+            },
+            None => Hint {
+                message: "add a `[` where the loop should start".to_string(),
+                span: None,
+            },
+        };
+        diagnostics.push(lines.diagnostic(close, "unmatched `]`", "no loop to close here", hint));
+    }
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+    diagnostics
+}
+
+/// Parses brainfuck source code into a tree of tokens, each with the span of
+/// the character it comes from.
+///
+/// Fails with [`Error::Syntax`] listing every unmatched `[` and `]`.
+pub fn parse(source: &str) -> Result<Vec<Token>, Error> {
+    let mut brackets = Brackets::default();
+    let tokens = compile(source.as_bytes(), &mut brackets);
+    if brackets.unmatched_open.is_empty() && brackets.unmatched_close.is_empty() {
+        Ok(tokens)
+    } else {
+        Err(Error::Syntax(diagnostics(source, &brackets)))
+    }
+}
@@ -0,0 +1,234 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    io::{Read, Write},
+};
+
+use crate::{
+    bytecode::{Instruction, Program},
+    cell::Cell,
+    error::Error,
+    interpreter::Interpreter,
+    token::Span,
+};
+
+/// How many hot spots [`Profile::report`] lists.
+const HOT_SPOTS: usize = 20;
+
+/// How a loop of a profiled program ran.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoopProfile {
+    /// From the `[` to the `]`.
+    pub span: Span,
+    /// Times the loop was reached.
+    pub entries: u64,
+    /// Times its body was run.
+    pub iterations: u64,
+    /// Instructions executed inside it, nested loops included.
+    pub instructions: u64,
+}
+
+/// Execution counts of every instruction of a program, gathered by
+/// [`Interpreter::profile`].
+#[derive(Debug, Clone)]
+pub struct Profile {
+    program: Program,
+    /// `counts[pc]` is how many times `instructions[pc]` was executed.
+    counts: Vec<u64>,
+}
+
+impl Profile {
+    /// Total number of instructions executed.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+    /// Instructions executed per source span, the most executed first.
+    ///
+    /// Optimized instructions span every command they replace, so a span can
+    /// cover more than one character.
+    pub fn locations(&self) -> Vec<(Span, u64)> {
+        let mut counts: BTreeMap<(usize, usize), u64> = BTreeMap::new();
+        for (span, &count) in self.program.spans.iter().zip(&self.counts) {
+            if count > 0 {
+                *counts.entry((span.start, span.end)).or_insert(0) += count;
+            }
+        }
+        let mut locations: Vec<(Span, u64)> = counts
+            .into_iter()
+            .map(|((start, end), count)| (Span::new(start, end), count))
+            .collect();
+        locations.sort_by_key(|&(span, count)| (u64::MAX - count, span.start));
+        locations
+    }
+    /// Every loop in source order.
+    pub fn loops(&self) -> Vec<LoopProfile> {
+        let spans = &self.program.spans;
+        self.program
+            .instructions
+            .iter()
+            .enumerate()
+            .filter_map(|(open, instruction)| match *instruction {
+                Instruction::JumpIfZero(end) => {
+                    let close = end - 1;
+                    Some(LoopProfile {
+                        span: spans[open].to(spans[close]),
+                        entries: self.counts[open],
+                        // Every run of the body ends on the `]`
+                        iterations: self.counts[close],
+                        instructions: self.counts[open + 1..close].iter().sum(),
+                    })
+                }
+                _ => None,
+            })
+            .collect()
+    }
+    /// The counts in the folded stack format read by flamegraph tools: one
+    /// line per stack of nested loops, with the instructions executed directly
+    /// in the innermost one. The outermost frame is `root`, loops are named
+    /// `loop@<line>:<column>` after their `[`.
+    pub fn folded(&self, source: &str, root: &str) -> String {
+        // Stacks are the pcs of the `[` of the open loops, so they sort in source order
+        let mut stacks: BTreeMap<Vec<usize>, u64> = BTreeMap::new();
+        let mut stack: Vec<usize> = Vec::new();
+        for (pc, (instruction, &count)) in self
+            .program
+            .instructions
+            .iter()
+            .zip(&self.counts)
+            .enumerate()
+        {
+            // The brackets belong to the loop they delimit
+            if let Instruction::JumpIfZero(_) = instruction {
+                stack.push(pc);
+            }
+            if count > 0 {
+                *stacks.entry(stack.clone()).or_insert(0) += count;
+            }
+            if let Instruction::JumpIfNotZero(_) = instruction {
+                stack.pop();
+            }
+        }
+        let mut folded = String::new();
+        for (stack, count) in stacks {
+            folded.push_str(&root.replace([';', ' '], "_"));
+            for pc in stack {
+                let (line, column) = self.program.spans[pc].line_column(source);
+                write!(folded, ";loop@{line}:{column}").unwrap();
+            }
+            writeln!(folded, " {count}").unwrap();
+        }
+        folded
+    }
+    /// A human readable summary of the hot spots and of the loops.
+    pub fn report(&self, source: &str) -> String {
+        let total = self.total();
+        let share = |count: u64| count as f64 * 100.0 / total.max(1) as f64;
+        let location = |span: Span| {
+            let (line, column) = span.line_column(source);
+            format!("{line}:{column}")
+        };
+        let mut report = format!("Instructions executed: {total}\n\nHot spots:\n");
+        writeln!(
+            report,
+            "{:>14} {:>7}  {:<10} code",
+            "count", "share", "location"
+        )
+        .unwrap();
+        let locations = self.locations();
+        for &(span, count) in locations.iter().take(HOT_SPOTS) {
+            writeln!(
+                report,
+                "{count:>14} {:>6.2}%  {:<10} {}",
+                share(count),
+                location(span),
+                code(source, span)
+            )
+            .unwrap();
+        }
+        if locations.len() > HOT_SPOTS {
+            writeln!(report, "{:>14} more", locations.len() - HOT_SPOTS).unwrap();
+        }
+
+        let mut loops = self.loops();
+        if loops.is_empty() {
+            return report;
+        }
+        loops.sort_by_key(|profile| (u64::MAX - profile.instructions, profile.span.start));
+        writeln!(
+            report,
+            "\nLoops:\n{:>14} {:>7} {:>12} {:>10}  {:<10} code",
+            "instructions", "share", "iterations", "entries", "location"
+        )
+        .unwrap();
+        for profile in loops.iter().take(HOT_SPOTS) {
+            writeln!(
+                report,
+                "{:>14} {:>6.2}% {:>12} {:>10}  {:<10} {}",
+                profile.instructions,
+                share(profile.instructions),
+                profile.iterations,
+                profile.entries,
+                location(profile.span),
+                code(source, profile.span)
+            )
+            .unwrap();
+        }
+        if loops.len() > HOT_SPOTS {
+            writeln!(report, "{:>14} more", loops.len() - HOT_SPOTS).unwrap();
+        }
+        report
+    }
+}
+
+/// The commands in the span, without comments and shortened to fit a line.
+fn code(source: &str, span: Span) -> String {
+    const WIDTH: usize = 30;
+    let commands: String = source
+        .get(span.start..span.end)
+        .unwrap_or("")
+        .chars()
+        .filter(|c| "+-<>[].,".contains(*c))
+        .collect();
+    if commands.len() > WIDTH {
+        format!("{}...", &commands[..WIDTH - 3])
+    } else {
+        commands
+    }
+}
+
+impl<C: Cell> Interpreter<C> {
+    /// Executes bytecode like [`execute`](Self::execute), counting how many
+    /// times each instruction runs.
+    ///
+    /// The profile is returned even if the program stopped with an error, or
+    /// because of a limit, with the counts up to that point. The instruction
+    /// that failed is not counted.
+    pub fn profile<R: Read, W: Write>(
+        &mut self,
+        program: &Program,
+        input: &mut R,
+        output: &mut W,
+    ) -> (Profile, Result<(), Error>) {
+        self.start();
+        let mut counts = vec![0; program.instructions.len()];
+        let mut pc = 0;
+        let mut result = Ok(());
+        while pc < program.instructions.len() {
+            match self.execute_at(&program.instructions, pc, input, output) {
+                Ok(next) => {
+                    counts[pc] += 1;
+                    pc = next;
+                }
+                Err(e) => {
+                    result = Err(e.at(program.spans[pc]));
+                    break;
+                }
+            }
+        }
+        let profile = Profile {
+            program: program.clone(),
+            counts,
+        };
+        (profile, result)
+    }
+}
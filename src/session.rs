@@ -0,0 +1,91 @@
+use std::io::{Read, Write};
+
+use crate::{
+    bytecode::Program, cell::Cell, config::Options, error::Error, interpreter::Interpreter,
+};
+
+/// How many changes [`Session::undo`] can revert.
+pub const UNDO_LIMIT: usize = 100;
+
+/// A tape kept between the programs run on it, like the lines typed in a
+/// REPL, with the states before its last changes.
+pub struct Session<C: Cell = u8> {
+    options: Options,
+    interpreter: Interpreter<C>,
+    history: Vec<Interpreter<C>>,
+}
+
+impl<C: Cell> Session<C> {
+    pub fn new(options: Options) -> Self {
+        Self {
+            options,
+            interpreter: Interpreter::new(options),
+            history: Vec::new(),
+        }
+    }
+    /// The current state of the tape.
+    pub fn interpreter(&self) -> &Interpreter<C> {
+        &self.interpreter
+    }
+    /// Saves the current state, to be restored by [`Session::undo`].
+    fn save(&mut self) {
+        if self.history.len() == UNDO_LIMIT {
+            self.history.remove(0);
+        }
+        self.history.push(self.interpreter.clone());
+    }
+    /// Runs `program` from the current state of the tape. The cells changed
+    /// before an error are kept, [`Session::undo`] reverts them.
+    pub fn run<R: Read, W: Write>(
+        &mut self,
+        program: &Program,
+        input: &mut R,
+        output: &mut W,
+    ) -> Result<(), Error> {
+        self.save();
+        self.interpreter.execute(program, input, output)
+    }
+    /// Sets the cell at `position` relative to cell 0, nothing changes if it
+    /// is outside a fixed tape.
+    pub fn set_cell(&mut self, position: isize, value: C) -> Result<(), Error> {
+        self.save();
+        let result = self.interpreter.set_cell(position, value);
+        if result.is_err() {
+            self.history.pop();
+        }
+        result
+    }
+    /// Clears the tape and moves the data pointer back to cell 0.
+    pub fn reset(&mut self) {
+        self.save();
+        self.interpreter = Interpreter::new(self.options);
+    }
+    /// Restores the state from before the last change, returns false if
+    /// there is nothing left to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some(interpreter) => {
+                self.interpreter = interpreter;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Whether `source` has an unclosed `[` and no unmatched `]`, so that more
+/// lines are needed to complete it.
+pub fn unclosed(source: &str) -> bool {
+    let mut depth: usize = 0;
+    for byte in source.bytes() {
+        match byte {
+            b'[' => depth += 1,
+            b']' => match depth.checked_sub(1) {
+                Some(outer) => depth = outer,
+                None => return false,
+            },
+            _ => (),
+        }
+    }
+    depth > 0
+}
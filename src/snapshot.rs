@@ -0,0 +1,444 @@
+use std::time::Duration;
+
+use crate::{
+    bytecode::{Instruction, Program},
+    cell::Cell,
+    config::{
+        CellWidth, EdgePolicy, EofPolicy, Flush, Limits, Options, OutputMode, Overflow, Tape,
+    },
+    error::Error,
+    interpreter::Interpreter,
+    machine::Machine,
+    token::Span,
+};
+
+const MAGIC: &[u8; 4] = b"BFSN";
+/// Version of the snapshot format written by [`Interpreter::snapshot`] and
+/// [`Machine::snapshot`], only snapshots of this version can be restored.
+pub const SNAPSHOT_VERSION: u16 = 1;
+
+const KIND_INTERPRETER: u8 = 0;
+const KIND_MACHINE: u8 = 1;
+
+struct Encoder(Vec<u8>);
+
+impl Encoder {
+    fn new(kind: u8) -> Self {
+        let mut encoder = Self(MAGIC.to_vec());
+        encoder.0.extend(SNAPSHOT_VERSION.to_le_bytes());
+        encoder.u8(kind);
+        encoder
+    }
+    fn u8(&mut self, value: u8) {
+        self.0.push(value)
+    }
+    fn u64(&mut self, value: u64) {
+        self.0.extend(value.to_le_bytes())
+    }
+    fn i64(&mut self, value: i64) {
+        self.0.extend(value.to_le_bytes())
+    }
+    fn option(&mut self, value: Option<u64>) {
+        self.u8(value.is_some() as u8);
+        self.u64(value.unwrap_or(0))
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    fn new(bytes: &'a [u8], kind: u8) -> Result<Self, Error> {
+        let mut decoder = Self { bytes };
+        if decoder.take(4)? != MAGIC {
+            return Err(Error::InvalidSnapshot("not a snapshot"));
+        }
+        if decoder.take(2)? != SNAPSHOT_VERSION.to_le_bytes() {
+            return Err(Error::InvalidSnapshot("unsupported version"));
+        }
+        if decoder.u8()? != kind {
+            return Err(Error::InvalidSnapshot("wrong kind of snapshot"));
+        }
+        Ok(decoder)
+    }
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.bytes.len() < len {
+            return Err(Error::InvalidSnapshot("truncated"));
+        }
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(bytes)
+    }
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.take(1)?[0])
+    }
+    fn u64(&mut self) -> Result<u64, Error> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+    fn i64(&mut self) -> Result<i64, Error> {
+        Ok(i64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+    fn usize(&mut self) -> Result<usize, Error> {
+        usize::try_from(self.u64()?).map_err(|_| Error::InvalidSnapshot("size too large"))
+    }
+    fn isize(&mut self) -> Result<isize, Error> {
+        isize::try_from(self.i64()?).map_err(|_| Error::InvalidSnapshot("offset too large"))
+    }
+    fn option(&mut self) -> Result<Option<u64>, Error> {
+        let present = self.u8()?;
+        let value = self.u64()?;
+        match present {
+            0 => Ok(None),
+            1 => Ok(Some(value)),
+            _ => Err(Error::InvalidSnapshot("invalid option")),
+        }
+    }
+    fn finish(self) -> Result<(), Error> {
+        match self.bytes {
+            [] => Ok(()),
+            _ => Err(Error::InvalidSnapshot("trailing bytes")),
+        }
+    }
+}
+
+fn width(cell: CellWidth) -> usize {
+    match cell {
+        CellWidth::U8 => 1,
+        CellWidth::U16 => 2,
+        CellWidth::U32 => 4,
+    }
+}
+
+fn encode_options(encoder: &mut Encoder, options: &Options) {
+    let (tape, size) = match options.tape {
+        Tape::Fixed(size) => (0, size as u64),
+        Tape::Unbounded => (1, 0),
+        Tape::Bidirectional => (2, 0),
+    };
+    encoder.u8(tape);
+    encoder.u64(size);
+    encoder.u8(match options.edge {
+        EdgePolicy::Wrap => 0,
+        EdgePolicy::Error => 1,
+        EdgePolicy::Clamp => 2,
+    });
+    encoder.u8(width(options.cell) as u8);
+    encoder.u8(match options.overflow {
+        Overflow::Wrap => 0,
+        Overflow::Saturate => 1,
+        Overflow::Trap => 2,
+    });
+    encoder.u8(match options.output {
+        OutputMode::Byte => 0,
+        OutputMode::Utf8 => 1,
+        OutputMode::Decimal => 2,
+    });
+    encoder.u8(match options.eof {
+        EofPolicy::Zero => 0,
+        EofPolicy::MinusOne => 1,
+        EofPolicy::Unchanged => 2,
+    });
+    encoder.u8(match options.flush {
+        Flush::BeforeInput => 0,
+        Flush::AtExit => 1,
+    });
+    let limits = &options.limits;
+    encoder.option(limits.steps);
+    encoder.option(
+        limits
+            .time
+            .map(|time| time.as_nanos().min(u64::MAX as u128) as u64),
+    );
+    encoder.option(limits.output);
+    encoder.option(limits.tape.map(|cells| cells as u64));
+}
+
+fn decode_options(decoder: &mut Decoder) -> Result<Options, Error> {
+    let tape = match (decoder.u8()?, decoder.usize()?) {
+        (0, 0) => return Err(Error::InvalidSnapshot("empty tape")),
+        (0, size) => Tape::Fixed(size),
+        (1, _) => Tape::Unbounded,
+        (2, _) => Tape::Bidirectional,
+        _ => return Err(Error::InvalidSnapshot("invalid tape")),
+    };
+    let edge = match decoder.u8()? {
+        0 => EdgePolicy::Wrap,
+        1 => EdgePolicy::Error,
+        2 => EdgePolicy::Clamp,
+        _ => return Err(Error::InvalidSnapshot("invalid edge policy")),
+    };
+    let cell = match decoder.u8()? {
+        1 => CellWidth::U8,
+        2 => CellWidth::U16,
+        4 => CellWidth::U32,
+        _ => return Err(Error::InvalidSnapshot("invalid cell width")),
+    };
+    let overflow = match decoder.u8()? {
+        0 => Overflow::Wrap,
+        1 => Overflow::Saturate,
+        2 => Overflow::Trap,
+        _ => return Err(Error::InvalidSnapshot("invalid overflow mode")),
+    };
+    let output = match decoder.u8()? {
+        0 => OutputMode::Byte,
+        1 => OutputMode::Utf8,
+        2 => OutputMode::Decimal,
+        _ => return Err(Error::InvalidSnapshot("invalid output mode")),
+    };
+    let eof = match decoder.u8()? {
+        0 => EofPolicy::Zero,
+        1 => EofPolicy::MinusOne,
+        2 => EofPolicy::Unchanged,
+        _ => return Err(Error::InvalidSnapshot("invalid EOF policy")),
+    };
+    let flush = match decoder.u8()? {
+        0 => Flush::BeforeInput,
+        1 => Flush::AtExit,
+        _ => return Err(Error::InvalidSnapshot("invalid flush mode")),
+    };
+    let limits = Limits {
+        steps: decoder.option()?,
+        time: decoder.option()?.map(Duration::from_nanos),
+        output: decoder.option()?,
+        tape: decoder
+            .option()?
+            .map(usize::try_from)
+            .transpose()
+            .map_err(|_| Error::InvalidSnapshot("size too large"))?,
+    };
+    Ok(Options {
+        tape,
+        edge,
+        cell,
+        overflow,
+        output,
+        eof,
+        flush,
+        limits,
+    })
+}
+
+impl<C: Cell> Interpreter<C> {
+    /// Saves the options, the tape, the data pointer and the usage counted
+    /// against the limits, to be restored with [`restore`](Self::restore).
+    ///
+    /// The snapshot is binary, all integers are little endian:
+    ///
+    /// | Size | Field |
+    /// | --- | --- |
+    /// | 4 | `BFSN` |
+    /// | 2 | version, [`SNAPSHOT_VERSION`] |
+    /// | 1 | kind: 0 for an interpreter, 1 for a [`Machine`] |
+    /// | 1 + 8 | tape: 0 and the size if fixed, 1 if unbounded, 2 if bidirectional |
+    /// | 1 | edge policy: 0 wrap, 1 error, 2 clamp |
+    /// | 1 | cell width in bytes: 1, 2 or 4 |
+    /// | 1 | overflow: 0 wrap, 1 saturate, 2 trap |
+    /// | 1 | output mode: 0 byte, 1 utf8, 2 decimal |
+    /// | 1 | EOF policy: 0 zero, 1 minus one, 2 unchanged |
+    /// | 1 | flush: 0 before input, 1 at exit |
+    /// | 4 × (1 + 8) | limits on steps, time in nanoseconds, output bytes and tape cells: 1 and the limit if set, 0 and 0 otherwise |
+    /// | 8 | steps executed |
+    /// | 8 | bytes written |
+    /// | 8 | index of cell 0 in the tape |
+    /// | 8 | index of the data pointer in the tape |
+    /// | 8 | number of cells n |
+    /// | n × width | cells |
+    ///
+    /// A machine snapshot continues with:
+    ///
+    /// | Size | Field |
+    /// | --- | --- |
+    /// | 8 | index of the next instruction |
+    /// | 1 | 1 if the input is closed |
+    /// | 8 | number of queued input bytes m |
+    /// | m | queued input |
+    /// | 8 | number of instructions k |
+    /// | k × 17 | opcode and two operands, see below |
+    /// | k × 16 | start and end of the span of each instruction |
+    ///
+    /// Opcodes are in the order of [`Instruction`], from 0 for `Output` to 12
+    /// for `JumpIfNotZero`. Operands are signed, unused ones are 0, `MulAdd`,
+    /// `Add` and `Set` store the offset first.
+    pub fn snapshot(&self) -> Vec<u8> {
+        let mut encoder = Encoder::new(KIND_INTERPRETER);
+        self.encode(&mut encoder);
+        encoder.0
+    }
+    /// Recreates an interpreter from a [`snapshot`](Self::snapshot) with the
+    /// same cell width. The time limit starts again from now.
+    pub fn restore(snapshot: &[u8]) -> Result<Self, Error> {
+        let mut decoder = Decoder::new(snapshot, KIND_INTERPRETER)?;
+        let interpreter = Self::decode(&mut decoder)?;
+        decoder.finish()?;
+        Ok(interpreter)
+    }
+    fn encode(&self, encoder: &mut Encoder) {
+        encode_options(encoder, &self.options);
+        encoder.u64(self.steps);
+        encoder.u64(self.written);
+        encoder.u64(self.origin as u64);
+        encoder.u64(self.dp as u64);
+        encoder.u64(self.memory.len() as u64);
+        let width = width(C::WIDTH);
+        for cell in &self.memory {
+            encoder.0.extend(&cell.to_u32().to_le_bytes()[..width]);
+        }
+    }
+    fn decode(decoder: &mut Decoder) -> Result<Self, Error> {
+        let options = decode_options(decoder)?;
+        if options.cell != C::WIDTH {
+            return Err(Error::InvalidSnapshot("different cell width"));
+        }
+        let (steps, written) = (decoder.u64()?, decoder.u64()?);
+        let (origin, dp, len) = (decoder.usize()?, decoder.usize()?, decoder.usize()?);
+        let width = width(C::WIDTH);
+        let size = len
+            .checked_mul(width)
+            .ok_or(Error::InvalidSnapshot("size too large"))?;
+        let cells = decoder.take(size)?;
+        let valid = match options.tape {
+            Tape::Fixed(size) => len == size && origin == 0,
+            Tape::Unbounded => len > 0 && origin == 0,
+            Tape::Bidirectional => origin < len,
+        };
+        if !valid || dp >= len {
+            return Err(Error::InvalidSnapshot("inconsistent tape"));
+        }
+        // Allocates the fixed tape only once its cells are known to be there
+        let mut interpreter = Self::new(options);
+        interpreter.memory = cells
+            .chunks(width)
+            .map(|bytes| {
+                let mut value = [0; 4];
+                value[..width].copy_from_slice(bytes);
+                C::from_u32(u32::from_le_bytes(value)).unwrap()
+            })
+            .collect();
+        interpreter.origin = origin;
+        interpreter.dp = dp;
+        interpreter.steps = steps;
+        interpreter.written = written;
+        interpreter.start_clock();
+        Ok(interpreter)
+    }
+}
+
+fn encode_instruction(encoder: &mut Encoder, instruction: Instruction) {
+    let (opcode, first, second) = match instruction {
+        Instruction::Output => (0, 0, 0),
+        Instruction::Input => (1, 0, 0),
+        Instruction::Move(v) => (2, v, 0),
+        Instruction::IncValue(v) => (3, v, 0),
+        Instruction::SetZero => (4, 0, 0),
+        Instruction::Scan(step) => (5, step, 0),
+        Instruction::MulAdd { offset, factor } => (6, offset, factor),
+        Instruction::Add { offset, value } => (7, offset, value),
+        Instruction::Set { offset, value } => (8, offset, value),
+        Instruction::OutputAt { offset } => (9, offset, 0),
+        Instruction::InputAt { offset } => (10, offset, 0),
+        Instruction::JumpIfZero(target) => (11, target as isize, 0),
+        Instruction::JumpIfNotZero(target) => (12, target as isize, 0),
+    };
+    encoder.u8(opcode);
+    encoder.i64(first as i64);
+    encoder.i64(second as i64);
+}
+
+fn decode_instruction(decoder: &mut Decoder, len: usize) -> Result<Instruction, Error> {
+    let opcode = decoder.u8()?;
+    let (first, second) = (decoder.isize()?, decoder.isize()?);
+    let target = || match usize::try_from(first) {
+        Ok(target) if target <= len => Ok(target),
+        _ => Err(Error::InvalidSnapshot("jump out of the program")),
+    };
+    Ok(match opcode {
+        0 => Instruction::Output,
+        1 => Instruction::Input,
+        2 => Instruction::Move(first),
+        3 => Instruction::IncValue(first),
+        4 => Instruction::SetZero,
+        5 => Instruction::Scan(first),
+        6 => Instruction::MulAdd {
+            offset: first,
+            factor: second,
+        },
+        7 => Instruction::Add {
+            offset: first,
+            value: second,
+        },
+        8 => Instruction::Set {
+            offset: first,
+            value: second,
+        },
+        9 => Instruction::OutputAt { offset: first },
+        10 => Instruction::InputAt { offset: first },
+        11 => Instruction::JumpIfZero(target()?),
+        12 => Instruction::JumpIfNotZero(target()?),
+        _ => return Err(Error::InvalidSnapshot("invalid instruction")),
+    })
+}
+
+impl<C: Cell> Machine<C> {
+    /// Saves the interpreter, the program, the position in it and the queued
+    /// input. The format is described in [`Interpreter::snapshot`].
+    ///
+    /// A snapshot can be restored many times, to continue from the same point
+    /// with different inputs.
+    pub fn snapshot(&self) -> Vec<u8> {
+        let mut encoder = Encoder::new(KIND_MACHINE);
+        self.interpreter.encode(&mut encoder);
+        encoder.u64(self.pc as u64);
+        encoder.u8(self.closed as u8);
+        encoder.u64(self.input.len() as u64);
+        encoder.0.extend(&self.input);
+        encoder.u64(self.program.instructions.len() as u64);
+        for &instruction in &self.program.instructions {
+            encode_instruction(&mut encoder, instruction);
+        }
+        for span in &self.program.spans {
+            encoder.u64(span.start as u64);
+            encoder.u64(span.end as u64);
+        }
+        encoder.0
+    }
+    /// Recreates a machine from a [`snapshot`](Self::snapshot) with the same
+    /// cell width. The time limit starts again from now.
+    pub fn restore(snapshot: &[u8]) -> Result<Self, Error> {
+        let mut decoder = Decoder::new(snapshot, KIND_MACHINE)?;
+        let interpreter = Interpreter::decode(&mut decoder)?;
+        let pc = decoder.usize()?;
+        let closed = match decoder.u8()? {
+            0 => false,
+            1 => true,
+            _ => return Err(Error::InvalidSnapshot("invalid input state")),
+        };
+        let queued = decoder.usize()?;
+        let input = decoder.take(queued)?.iter().copied().collect();
+        let len = decoder.usize()?;
+        // Every instruction takes 33 bytes, checked before allocating
+        if decoder.bytes.len() / 33 < len {
+            return Err(Error::InvalidSnapshot("truncated"));
+        }
+        let instructions = (0..len)
+            .map(|_| decode_instruction(&mut decoder, len))
+            .collect::<Result<Vec<_>, _>>()?;
+        let spans = (0..len)
+            .map(|_| Ok(Span::new(decoder.usize()?, decoder.usize()?)))
+            .collect::<Result<Vec<_>, Error>>()?;
+        decoder.finish()?;
+        if pc > len {
+            return Err(Error::InvalidSnapshot("position out of the program"));
+        }
+        Ok(Self {
+            interpreter,
+            program: Program {
+                instructions,
+                spans,
+            },
+            pc,
+            input,
+            closed,
+        })
+    }
+}
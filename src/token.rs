@@ -0,0 +1,241 @@
+use std::{fmt, mem, slice};
+
+/// Byte range of the source code a token comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+    /// The smallest span covering both spans.
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+    /// Line and column, both starting from 1, of the start of the span.
+    pub fn line_column(&self, source: &str) -> (u32, u32) {
+        let before = &source.as_bytes()[..self.start.min(source.len())];
+        let line = before.iter().filter(|&&byte| byte == b'\n').count() + 1;
+        let line_start = before
+            .iter()
+            .rposition(|&byte| byte == b'\n')
+            .map_or(0, |newline| newline + 1);
+        (line as u32, (before.len() - line_start + 1) as u32)
+    }
+    /// The source line where the span starts, with carets under the span:
+    ///
+    /// ```text
+    ///  2 | +[->+<]
+    ///    |  ^^^^^^
+    /// ```
+    pub fn snippet(&self, source: &str) -> String {
+        let (line, column) = self.line_column(source);
+        let text = source.lines().nth(line as usize - 1).unwrap_or("");
+        let start = (column as usize - 1).min(text.len());
+        let end = (start + self.end.saturating_sub(self.start)).min(text.len());
+        // Tabs are kept so that the carets line up, spans covering more lines
+        // are underlined up to the end of the first one
+        let padding: String = text[..start]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let carets = text.get(start..end).map_or(0, |s| s.chars().count()).max(1);
+        let number = line.to_string();
+        let margin = " ".repeat(number.len());
+        format!(
+            " {number} | {text}\n {margin} | {padding}{}",
+            "^".repeat(carets)
+        )
+    }
+}
+
+/// A token of the program, loops hold the tokens of their body.
+///
+/// [`Clone`], [`PartialEq`], [`Debug`](fmt::Debug) and [`Drop`] go through
+/// nested loops without recursing, so that they work at any depth.
+pub struct Token {
+    pub kind: TokenKind,
+    /// Covers every character the token was built from.
+    pub span: Span,
+}
+
+impl Token {
+    pub fn new(kind: TokenKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+    /// The kind and the span of the token, which can't be moved out of it
+    /// directly because it implements [`Drop`].
+    pub fn into_parts(mut self) -> (TokenKind, Span) {
+        (mem::replace(&mut self.kind, TokenKind::Output), self.span)
+    }
+}
+
+impl Drop for Token {
+    #[inline]
+    fn drop(&mut self) {
+        if let TokenKind::Loop(body) = &mut self.kind {
+            drop_nested(mem::take(body));
+        }
+    }
+}
+
+impl Clone for Token {
+    fn clone(&self) -> Self {
+        // The tokens of the loops being copied, the outermost first
+        let mut open: Vec<Vec<Token>> = Vec::new();
+        let mut tokens = Vec::new();
+        for visit in walk(slice::from_ref(self)) {
+            match visit {
+                Visit::Token(token) => tokens.push(Token::new(token.kind.clone(), token.span)),
+                Visit::Open(_) => open.push(mem::take(&mut tokens)),
+                Visit::Close(token) => {
+                    let body = mem::replace(&mut tokens, open.pop().unwrap());
+                    tokens.push(Token::new(TokenKind::Loop(body), token.span));
+                }
+            }
+        }
+        tokens.pop().unwrap()
+    }
+}
+
+impl PartialEq for Token {
+    fn eq(&self, other: &Self) -> bool {
+        let mut other = walk(slice::from_ref(other));
+        for visit in walk(slice::from_ref(self)) {
+            let same = match (visit, other.next()) {
+                (Visit::Token(a), Some(Visit::Token(b))) => a.kind == b.kind && a.span == b.span,
+                (Visit::Open(a), Some(Visit::Open(b)))
+                | (Visit::Close(a), Some(Visit::Close(b))) => a.span == b.span,
+                _ => false,
+            };
+            if !same {
+                return false;
+            }
+        }
+        other.next().is_none()
+    }
+}
+
+/// Written like a derived implementation, always on a single line.
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Whether the next token is the first of its loop
+        let mut first = true;
+        for visit in walk(slice::from_ref(self)) {
+            if !mem::take(&mut first) && !matches!(visit, Visit::Close(_)) {
+                write!(f, ", ")?;
+            }
+            match visit {
+                Visit::Token(token) => write!(
+                    f,
+                    "Token {{ kind: {:?}, span: {:?} }}",
+                    token.kind, token.span
+                )?,
+                Visit::Open(_) => {
+                    write!(f, "Token {{ kind: Loop([")?;
+                    first = true;
+                }
+                Visit::Close(token) => write!(f, "]), span: {:?} }}", token.span)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Drops the body of a loop moving the bodies of the nested loops to a single
+/// list, so that deep nesting doesn't overflow the stack.
+fn drop_nested(mut tokens: Vec<Token>) {
+    while let Some(mut token) = tokens.pop() {
+        if let TokenKind::Loop(body) = &mut token.kind {
+            tokens.append(body);
+        }
+    }
+}
+
+/// A step of [`walk`].
+pub(crate) enum Visit<'a> {
+    /// A token other than a loop.
+    Token(&'a Token),
+    /// The `[` of a loop, the tokens of its body follow.
+    Open(&'a Token),
+    /// The `]` of a loop.
+    Close(&'a Token),
+}
+
+/// Iterator returned by [`walk`].
+pub(crate) struct Walk<'a> {
+    /// The rest of the tokens of every level, the outermost first.
+    levels: Vec<slice::Iter<'a, Token>>,
+    /// The loops whose bodies are the levels after the first.
+    loops: Vec<&'a Token>,
+}
+
+impl<'a> Iterator for Walk<'a> {
+    type Item = Visit<'a>;
+
+    fn next(&mut self) -> Option<Visit<'a>> {
+        match self.levels.last_mut()?.next() {
+            Some(token) => match &token.kind {
+                TokenKind::Loop(body) => {
+                    self.levels.push(body.iter());
+                    self.loops.push(token);
+                    Some(Visit::Open(token))
+                }
+                _ => Some(Visit::Token(token)),
+            },
+            None => {
+                self.levels.pop();
+                self.loops.pop().map(Visit::Close)
+            }
+        }
+    }
+}
+
+/// Visits the tokens in source order, entering loops without recursing, so
+/// that the nesting depth is only limited by memory.
+pub(crate) fn walk(tokens: &[Token]) -> Walk<'_> {
+    Walk {
+        levels: vec![tokens.iter()],
+        loops: Vec::new(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    Output,
+    Input,
+    Move(isize),
+    IncValue(isize),
+    Loop(Vec<Token>),
+    /// `[-]` or `[+]`: sets the current cell to zero.
+    SetZero,
+    /// `[>]`, `[<<]`...: moves by the step until a zero cell is found.
+    Scan(isize),
+    /// Adds the current cell times `factor` to the cell at `offset` from the
+    /// data pointer, does nothing if the current cell is zero.
+    MulAdd {
+        offset: isize,
+        factor: isize,
+    },
+    /// Adds `value` to the cell at `offset` from the data pointer.
+    Add {
+        offset: isize,
+        value: isize,
+    },
+    /// Sets the cell at `offset` to zero plus `value`.
+    Set {
+        offset: isize,
+        value: isize,
+    },
+    /// `.` on the cell at `offset`.
+    OutputAt {
+        offset: isize,
+    },
+    /// `,` on the cell at `offset`.
+    InputAt {
+        offset: isize,
+    },
+}
@@ -0,0 +1,134 @@
+//! The C backend must behave like the interpreter. Compiles the generated code
+//! with `cc`, the tests pass without checking anything when it is missing.
+
+use std::{
+    fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use mindfuck::{
+    emit_c, optimize, parse, CellWidth, EdgePolicy, EofPolicy, Options, OutputMode, Overflow, Tape,
+};
+
+mod common;
+
+use common::{assert_same, available, INPUT};
+
+/// Output of the compiled C program, with its standard error if it failed.
+fn compile_and_run(source: &str, options: Options, name: &str) -> (Vec<u8>, Option<String>) {
+    let tokens = optimize(parse(source).unwrap(), &options);
+    let directory = std::env::temp_dir();
+    let path = |extension: &str| -> PathBuf {
+        directory.join(format!(
+            "mindfuck-c-{}-{name}{extension}",
+            std::process::id()
+        ))
+    };
+    fs::write(path(".c"), emit_c(&tokens, &options)).unwrap();
+    let status = Command::new("cc")
+        .arg("-O1")
+        .arg("-o")
+        .arg(path(""))
+        .arg(path(".c"))
+        .status()
+        .unwrap();
+    assert!(status.success(), "cc failed on {source:?} with {options:?}");
+
+    let mut child = Command::new(path(""))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // The program can exit without reading all of it
+    let _ = child.stdin.take().unwrap().write_all(INPUT);
+    let output = child.wait_with_output().unwrap();
+    fs::remove_file(path(".c")).unwrap();
+    fs::remove_file(path("")).unwrap();
+    let error =
+        (!output.status.success()).then(|| String::from_utf8_lossy(&output.stderr).into_owned());
+    (output.stdout, error)
+}
+
+#[test]
+fn tapes_and_edges() {
+    if !available("cc") {
+        return eprintln!("cc not found, skipped");
+    }
+    for (name, tape, edge) in [
+        ("wrap", Tape::Fixed(16), EdgePolicy::Wrap),
+        ("error", Tape::Fixed(16), EdgePolicy::Error),
+        ("clamp", Tape::Fixed(16), EdgePolicy::Clamp),
+        ("unbounded", Tape::Unbounded, EdgePolicy::Wrap),
+        ("unbounded-clamp", Tape::Unbounded, EdgePolicy::Clamp),
+        ("bidirectional", Tape::Bidirectional, EdgePolicy::Wrap),
+    ] {
+        let options = Options {
+            tape,
+            edge,
+            ..Options::default()
+        };
+        assert_same(options, name, compile_and_run);
+    }
+}
+
+#[test]
+fn cells_and_overflow() {
+    if !available("cc") {
+        return eprintln!("cc not found, skipped");
+    }
+    for (name, cell, overflow) in [
+        ("u16", CellWidth::U16, Overflow::Wrap),
+        ("u32", CellWidth::U32, Overflow::Wrap),
+        ("saturate", CellWidth::U8, Overflow::Saturate),
+        ("trap", CellWidth::U8, Overflow::Trap),
+        ("u16-trap", CellWidth::U16, Overflow::Trap),
+    ] {
+        let options = Options {
+            tape: Tape::Fixed(64),
+            cell,
+            overflow,
+            ..Options::default()
+        };
+        assert_same(options, name, compile_and_run);
+    }
+}
+
+#[test]
+fn input_and_output() {
+    if !available("cc") {
+        return eprintln!("cc not found, skipped");
+    }
+    for (name, eof, output, cell) in [
+        (
+            "minus-one",
+            EofPolicy::MinusOne,
+            OutputMode::Byte,
+            CellWidth::U8,
+        ),
+        (
+            "unchanged",
+            EofPolicy::Unchanged,
+            OutputMode::Byte,
+            CellWidth::U8,
+        ),
+        (
+            "decimal",
+            EofPolicy::Zero,
+            OutputMode::Decimal,
+            CellWidth::U16,
+        ),
+        ("utf8", EofPolicy::Zero, OutputMode::Utf8, CellWidth::U32),
+    ] {
+        let options = Options {
+            tape: Tape::Fixed(64),
+            eof,
+            output,
+            cell,
+            ..Options::default()
+        };
+        assert_same(options, name, compile_and_run);
+    }
+}
@@ -0,0 +1,74 @@
+//! Programs and helpers shared by the tests comparing the backends with the
+//! interpreter.
+// Every test crate uses only some of them
+#![allow(dead_code)]
+
+use std::process::{Command, Stdio};
+
+use mindfuck::{lower, optimize, parse, Cell, CellWidth, Interpreter, Options};
+
+pub const INPUT: &[u8] = b"Hello, World!\n\xff";
+
+pub const PROGRAMS: &[&str] = &[
+    include_str!("../../benches/programs/hello.b"),
+    // Echoes the first line, then prints it backwards
+    ",----------[++++++++++.,----------]",
+    ">,----------[++++++++++>,----------]<[.<]",
+    // Reads past the end of the input
+    ",,,,,,,,,,,,,,,.,.,.",
+    "++++++[-->+<]>.",
+    "-[->+>++<<]>.>.",
+    "+>+>+>[<]<+.",
+    "-.+.",
+    "<<<+.>>>>>>>>.",
+    ">>>>>>>>>>>>>>>>>>>>+.",
+    // Reads cells that need the tape to grow first
+    "+>>>>>>>>>>>>>>>>>>.<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<.",
+];
+
+/// Whether `command --version` runs.
+pub fn available(command: &str) -> bool {
+    Command::new(command)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Output of the interpreter, with the message of its error if any.
+pub fn interpret<C: Cell>(source: &str, options: Options) -> (Vec<u8>, Option<String>) {
+    let tokens = optimize(parse(source).unwrap(), &options);
+    let mut output = Vec::new();
+    let result =
+        Interpreter::<C>::new(options).execute(&lower(&tokens), &mut &INPUT[..], &mut output);
+    (output, result.err().map(|e| e.inner().to_string()))
+}
+
+/// Runs every program with `run`, given the source, the options and a name
+/// for its files, and compares it with the interpreter. `run` returns the
+/// output and, if it failed, a message that must contain the error of the
+/// interpreter, or is empty when the backend can't tell why it failed.
+pub fn assert_same(
+    options: Options,
+    name: &str,
+    run: impl Fn(&str, Options, &str) -> (Vec<u8>, Option<String>),
+) {
+    for (i, source) in PROGRAMS.iter().enumerate() {
+        let (expected, error) = match options.cell {
+            CellWidth::U8 => interpret::<u8>(source, options),
+            CellWidth::U16 => interpret::<u16>(source, options),
+            CellWidth::U32 => interpret::<u32>(source, options),
+        };
+        let (output, message) = run(source, options, &format!("{name}-{i}"));
+        assert_eq!(output, expected, "{source:?} with {options:?}");
+        match (error, message) {
+            (None, None) => (),
+            (Some(error), Some(message)) => assert!(
+                message.is_empty() || message.contains(&error),
+                "{message:?} should report {error:?}"
+            ),
+            (error, message) => panic!("{source:?} with {options:?}: {error:?} but {message:?}"),
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::io;
+
+use mindfuck::{Debugger, Options, Stop};
+
+fn debugger(source: &str) -> Debugger {
+    let mut debugger = Debugger::new(source, Options::default()).unwrap();
+    debugger.break_at_markers();
+    debugger
+}
+
+#[test]
+fn breakpoint_on_the_first_instruction() {
+    let mut debugger = debugger("#+++.");
+    let mut output = Vec::new();
+    assert_eq!(
+        debugger.resume(&mut io::empty(), &mut output).unwrap(),
+        Stop::Breakpoint
+    );
+    assert_eq!(debugger.span().unwrap().start, 1);
+    assert_eq!(debugger.interpreter().cell(0), Some(0));
+    assert_eq!(
+        debugger.resume(&mut io::empty(), &mut output).unwrap(),
+        Stop::Finished
+    );
+    assert_eq!(output, [3]);
+}
+
+#[test]
+fn breakpoint_in_a_loop_stops_every_iteration() {
+    let mut debugger = debugger("+++[#-]");
+    let mut values = Vec::new();
+    while debugger.resume(&mut io::empty(), &mut io::sink()).unwrap() == Stop::Breakpoint {
+        values.push(debugger.interpreter().cell(0).unwrap());
+    }
+    assert_eq!(values, [3, 2, 1]);
+}
+
+#[test]
+fn step_runs_an_instruction_with_a_breakpoint() {
+    let mut debugger = debugger("#++#+");
+    let (input, output) = (&mut io::empty(), &mut io::sink());
+    assert_eq!(debugger.step(input, output).unwrap(), Stop::Step);
+    assert_eq!(debugger.step(input, output).unwrap(), Stop::Breakpoint);
+    assert_eq!(debugger.step(input, output).unwrap(), Stop::Finished);
+    assert_eq!(debugger.interpreter().cell(0), Some(3));
+}
+
+#[test]
+fn breakpoint_after_a_watchpoint_on_the_same_instruction() {
+    let mut debugger = debugger("+#+");
+    debugger.watch(0, None);
+    let (input, output) = (&mut io::empty(), &mut io::sink());
+    assert_eq!(
+        debugger.resume(input, output).unwrap(),
+        Stop::Watchpoint { position: 0 }
+    );
+    // Already stopped before the second `+`
+    assert_eq!(
+        debugger.resume(input, output).unwrap(),
+        Stop::Watchpoint { position: 0 }
+    );
+    assert_eq!(debugger.resume(input, output).unwrap(), Stop::Finished);
+}
@@ -0,0 +1,146 @@
+//! Executables built for x86-64 Linux must behave like the interpreter: same
+//! output, exit code 1 on a runtime error and 0 otherwise.
+#![cfg(all(target_arch = "x86_64", target_os = "linux"))]
+
+use std::{
+    fs,
+    io::{ErrorKind, Write},
+    os::unix::fs::PermissionsExt,
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+};
+
+use mindfuck::{
+    build_elf, optimize, parse, CellWidth, EdgePolicy, EofPolicy, Error, Options, OutputMode,
+    Overflow, Tape,
+};
+
+mod common;
+
+use common::{assert_same, interpret, INPUT};
+
+const SAMPLES: [&str; 4] = [
+    include_str!("../benches/programs/hello.b"),
+    include_str!("../benches/programs/loops.b"),
+    include_str!("../benches/programs/hanoi.b"),
+    include_str!("../benches/programs/mandelbrot.b"),
+];
+
+/// Starts the executable, retrying while another test thread still holds it
+/// open for writing.
+fn spawn(path: &PathBuf) -> Child {
+    loop {
+        match Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+        {
+            Err(e) if e.kind() == ErrorKind::ExecutableFileBusy => std::thread::yield_now(),
+            result => return result.unwrap(),
+        }
+    }
+}
+
+/// Output of the executable, with an empty message if it exited with 1. It
+/// doesn't print where the data pointer went, so the message is not compared.
+fn build_and_run(source: &str, options: Options, name: &str) -> (Vec<u8>, Option<String>) {
+    let tokens = optimize(parse(source).unwrap(), &options);
+    let path = std::env::temp_dir().join(format!("mindfuck-elf-{}-{name}", std::process::id()));
+    fs::write(&path, build_elf(&tokens, &options).unwrap()).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut child = spawn(&path);
+    // The program can exit without reading all of it
+    let _ = child.stdin.take().unwrap().write_all(INPUT);
+    let output = child.wait_with_output().unwrap();
+    fs::remove_file(&path).unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    match output.status.code() {
+        Some(0) => (output.stdout, None),
+        Some(1) => {
+            assert!(stderr.contains("[RUNTIME ERROR]"), "{stderr:?}");
+            (output.stdout, Some(String::new()))
+        }
+        code => panic!("{source:?} with {options:?} exited with {code:?}: {stderr}"),
+    }
+}
+
+#[test]
+fn samples() {
+    for (i, source) in SAMPLES.iter().enumerate() {
+        let options = Options::default();
+        let expected = interpret::<u8>(source, options);
+        assert_eq!(expected.1, None);
+        assert_eq!(
+            build_and_run(source, options, &format!("sample-{i}")),
+            expected
+        );
+    }
+}
+
+#[test]
+fn edges() {
+    for (name, edge) in [
+        ("wrap", EdgePolicy::Wrap),
+        ("error", EdgePolicy::Error),
+        ("clamp", EdgePolicy::Clamp),
+    ] {
+        let options = Options {
+            tape: Tape::Fixed(16),
+            edge,
+            ..Options::default()
+        };
+        assert_same(options, name, build_and_run);
+    }
+}
+
+#[test]
+fn input_and_output() {
+    for (name, eof, output) in [
+        ("zero", EofPolicy::Zero, OutputMode::Byte),
+        ("minus-one", EofPolicy::MinusOne, OutputMode::Byte),
+        ("unchanged", EofPolicy::Unchanged, OutputMode::Byte),
+        ("utf8", EofPolicy::MinusOne, OutputMode::Utf8),
+    ] {
+        let options = Options {
+            tape: Tape::Fixed(64),
+            eof,
+            output,
+            ..Options::default()
+        };
+        assert_same(options, name, build_and_run);
+    }
+}
+
+#[test]
+fn unsupported_options_are_rejected() {
+    let tokens = parse("+.").unwrap();
+    for options in [
+        Options {
+            cell: CellWidth::U16,
+            ..Options::default()
+        },
+        Options {
+            cell: CellWidth::U32,
+            ..Options::default()
+        },
+        Options {
+            overflow: Overflow::Saturate,
+            ..Options::default()
+        },
+        Options {
+            output: OutputMode::Decimal,
+            ..Options::default()
+        },
+        Options {
+            tape: Tape::Unbounded,
+            ..Options::default()
+        },
+    ] {
+        assert!(
+            matches!(build_elf(&tokens, &options), Err(Error::Unsupported(_))),
+            "{options:?}"
+        );
+    }
+}
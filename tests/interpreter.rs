@@ -0,0 +1,304 @@
+use std::io::{self, Read, Write};
+
+use mindfuck::{
+    lower, optimize, parse, Cell, EdgePolicy, EofPolicy, Error, Flush, Interpreter, Limits,
+    Options, Overflow, Tape,
+};
+
+/// Output of the program, executed both on the tokens and on the bytecode.
+fn output<C: Cell>(source: &str, options: Options, input: &[u8]) -> Result<Vec<u8>, Error> {
+    let tokens = parse(source).unwrap();
+    let mut output = Vec::new();
+    Interpreter::<C>::new(options).run(&tokens, &mut &input[..], &mut output)?;
+    let mut executed = Vec::new();
+    Interpreter::<C>::new(options).execute(&lower(&tokens), &mut &input[..], &mut executed)?;
+    assert_eq!(executed, output, "{source:?}");
+    Ok(output)
+}
+
+/// Gives one byte per read, after an interruption.
+struct Trickle<'a> {
+    bytes: &'a [u8],
+    interrupted: bool,
+}
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        self.interrupted = !self.interrupted;
+        if self.interrupted {
+            return Err(io::ErrorKind::Interrupted.into());
+        }
+        let Some((&first, rest)) = self.bytes.split_first() else {
+            return Ok(0);
+        };
+        buffer[0] = first;
+        self.bytes = rest;
+        Ok(1)
+    }
+}
+
+struct Broken;
+
+impl Read for Broken {
+    fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+        Err(io::ErrorKind::BrokenPipe.into())
+    }
+}
+
+impl Write for Broken {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::ErrorKind::BrokenPipe.into())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Remembers how many bytes had been written at every flush.
+#[derive(Default)]
+struct Flushes {
+    written: usize,
+    flushes: Vec<usize>,
+}
+
+impl Write for Flushes {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.written += buf.len();
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.flushes.push(self.written);
+        Ok(())
+    }
+}
+
+#[test]
+fn reads_and_writes_the_given_streams() {
+    let echo = ",[.,]";
+    assert_eq!(
+        output::<u8>(echo, Options::default(), b"any stream").unwrap(),
+        b"any stream"
+    );
+
+    let tokens = parse(echo).unwrap();
+    let mut input = Trickle {
+        bytes: b"slow",
+        interrupted: false,
+    };
+    let mut output = Vec::new();
+    Interpreter::<u8>::default()
+        .run(&tokens, &mut input, &mut output)
+        .unwrap();
+    assert_eq!(output, b"slow");
+}
+
+#[test]
+fn stream_errors_are_returned() {
+    let tokens = parse(",.").unwrap();
+    let error = Interpreter::<u8>::default()
+        .run(&tokens, &mut Broken, &mut Vec::new())
+        .unwrap_err();
+    assert!(matches!(error.inner(), Error::Io(e) if e.kind() == io::ErrorKind::BrokenPipe));
+    let error = Interpreter::<u8>::default()
+        .run(&tokens, &mut &b"x"[..], &mut Broken)
+        .unwrap_err();
+    assert!(matches!(error.inner(), Error::Io(e) if e.kind() == io::ErrorKind::BrokenPipe));
+}
+
+/// The interpreter after running the program on the tape.
+fn run_on(source: &str, tape: Tape, edge: EdgePolicy) -> Result<Interpreter<u8>, Error> {
+    let options = Options {
+        tape,
+        edge,
+        ..Options::default()
+    };
+    let mut interpreter = Interpreter::new(options);
+    interpreter.run(&parse(source).unwrap(), &mut io::empty(), &mut io::sink())?;
+    Ok(interpreter)
+}
+
+#[test]
+fn edges_of_a_fixed_tape() {
+    let tape = Tape::Fixed(4);
+    let wrapped = run_on("<+", tape, EdgePolicy::Wrap).unwrap();
+    assert_eq!((wrapped.dp(), wrapped.cell(3)), (3, Some(1)));
+    let wrapped = run_on(">>>>+", tape, EdgePolicy::Wrap).unwrap();
+    assert_eq!((wrapped.dp(), wrapped.cell(0)), (0, Some(1)));
+
+    let clamped = run_on("<<+", tape, EdgePolicy::Clamp).unwrap();
+    assert_eq!((clamped.dp(), clamped.cell(0)), (0, Some(1)));
+    let clamped = run_on(">>>>>+", tape, EdgePolicy::Clamp).unwrap();
+    assert_eq!((clamped.dp(), clamped.cell(3)), (3, Some(1)));
+
+    for (source, dp, start) in [("+<", -1, 1), (">>>>", 4, 3)] {
+        let error = run_on(source, tape, EdgePolicy::Error).err().unwrap();
+        assert!(
+            matches!(error.inner(), &Error::MemoryOutOfBounds { dp: at } if at == dp),
+            "{source:?}: {error:?}"
+        );
+        assert_eq!(error.span().unwrap().start, start);
+    }
+    assert_eq!(run_on("+", tape, EdgePolicy::Error).unwrap().cell(4), None);
+}
+
+#[test]
+fn growing_tapes() {
+    let bidirectional = run_on("+<<<+<-", Tape::Bidirectional, EdgePolicy::Wrap).unwrap();
+    assert_eq!(bidirectional.dp(), -4);
+    let cells: Vec<_> = (-4..=1).map(|i| bidirectional.cell(i)).collect();
+    assert_eq!(cells, [255, 1, 0, 0, 1, 0].map(Some));
+
+    let unbounded = run_on(">>>>>>>>>>+", Tape::Unbounded, EdgePolicy::Wrap).unwrap();
+    assert_eq!((unbounded.dp(), unbounded.cell(10)), (10, Some(1)));
+    // The left edge can't be wrapped around or grown past
+    for edge in [EdgePolicy::Wrap, EdgePolicy::Error] {
+        let error = run_on(">+<<", Tape::Unbounded, edge).err().unwrap();
+        assert!(matches!(error.inner(), Error::MemoryOutOfBounds { dp: -1 }));
+    }
+    let clamped = run_on("<+", Tape::Unbounded, EdgePolicy::Clamp).unwrap();
+    assert_eq!(clamped.cell(0), Some(1));
+}
+
+#[test]
+fn empty_fixed_tape_has_one_cell() {
+    for edge in [EdgePolicy::Wrap, EdgePolicy::Clamp] {
+        let interpreter = run_on("+>+<+", Tape::Fixed(0), edge).unwrap();
+        assert_eq!(interpreter.cell(0), Some(3));
+    }
+    assert!(run_on("+>", Tape::Fixed(0), EdgePolicy::Error).is_err());
+}
+
+fn with_overflow(overflow: Overflow) -> Options {
+    Options {
+        overflow,
+        ..Options::default()
+    }
+}
+
+/// Value of cell 0 after running the program.
+fn cell<C: Cell>(source: &str, overflow: Overflow) -> Result<u32, Error> {
+    let mut interpreter = Interpreter::<C>::new(with_overflow(overflow));
+    interpreter.run(&parse(source).unwrap(), &mut io::empty(), &mut io::sink())?;
+    Ok(interpreter.cell(0).unwrap().to_u32())
+}
+
+#[test]
+fn cell_widths_wrap() {
+    assert_eq!(cell::<u8>("-", Overflow::Wrap).unwrap(), 255);
+    assert_eq!(cell::<u16>("-", Overflow::Wrap).unwrap(), 65535);
+    assert_eq!(cell::<u16>("-+", Overflow::Wrap).unwrap(), 0);
+    assert_eq!(cell::<u32>("-", Overflow::Wrap).unwrap(), u32::MAX);
+    // 256 increments only wrap an 8 bit cell
+    let source = "+".repeat(256);
+    assert_eq!(cell::<u8>(&source, Overflow::Wrap).unwrap(), 0);
+    assert_eq!(cell::<u16>(&source, Overflow::Wrap).unwrap(), 256);
+}
+
+#[test]
+fn overflow_saturates_or_traps() {
+    assert_eq!(cell::<u16>("--+", Overflow::Saturate).unwrap(), 1);
+    assert_eq!(
+        cell::<u8>(&"+".repeat(300), Overflow::Saturate).unwrap(),
+        255
+    );
+    assert_eq!(cell::<u8>(&"+".repeat(255), Overflow::Trap).unwrap(), 255);
+
+    let error = cell::<u16>(">-", Overflow::Trap).unwrap_err();
+    assert!(matches!(error.inner(), Error::CellOverflow { dp: 1 }));
+    assert_eq!(error.span().unwrap().start, 1);
+    let error = cell::<u8>(&"+".repeat(256), Overflow::Trap).unwrap_err();
+    assert!(matches!(error.inner(), Error::CellOverflow { dp: 0 }));
+    assert_eq!(error.span().unwrap().start, 255);
+}
+
+#[test]
+fn eof_policies() {
+    for (eof, after_input, on_seven, u16_value) in [
+        (EofPolicy::Zero, 0, 0, 0),
+        (EofPolicy::MinusOne, 255, 255, 65535),
+        (EofPolicy::Unchanged, b'x', 7, 7),
+    ] {
+        let options = Options {
+            eof,
+            ..Options::default()
+        };
+        assert_eq!(
+            output::<u8>(",.,.", options, b"x").unwrap(),
+            [b'x', after_input]
+        );
+        assert_eq!(output::<u8>("+++++++,.", options, b"").unwrap(), [on_seven]);
+
+        let mut interpreter = Interpreter::<u16>::new(options);
+        let tokens = parse("+++++++,").unwrap();
+        interpreter
+            .run(&tokens, &mut io::empty(), &mut io::sink())
+            .unwrap();
+        assert_eq!(interpreter.cell(0), Some(u16_value));
+    }
+}
+
+/// Without limits the bytecode runs on a faster path, which must behave like
+/// the one counting the steps.
+#[test]
+fn bytecode_runs_the_same_without_limits() {
+    let sources = [
+        include_str!("../benches/programs/hello.b"),
+        ">,[>,]<[.<]",
+        "+[>+]",
+        "+[<+]",
+        "+[->>+<<]>>.",
+        "<<+[>>>>]",
+        ",>,.<.",
+    ];
+    for edge in [EdgePolicy::Wrap, EdgePolicy::Error, EdgePolicy::Clamp] {
+        for source in sources {
+            let options = Options {
+                tape: Tape::Fixed(16),
+                edge,
+                ..Options::default()
+            };
+            let limited = Options {
+                limits: Limits {
+                    steps: Some(u64::MAX),
+                    ..Limits::default()
+                },
+                ..options
+            };
+            let program = lower(&optimize(parse(source).unwrap(), &options));
+            let results: Vec<_> = [options, limited]
+                .map(|options| {
+                    let mut interpreter = Interpreter::<u8>::new(options);
+                    let mut output = Vec::new();
+                    let result = interpreter.execute(&program, &mut &b"input"[..], &mut output);
+                    let error = result.err().map(|e| (e.span(), e.inner().to_string()));
+                    (output, error, interpreter.dp())
+                })
+                .into();
+            assert_eq!(results[0], results[1], "{source:?} with {edge:?}");
+        }
+    }
+}
+
+#[test]
+fn flush_policies() {
+    let tokens = parse("+.,.,.").unwrap();
+    for (flush, expected) in [(Flush::BeforeInput, vec![1, 2]), (Flush::AtExit, vec![])] {
+        let options = Options {
+            flush,
+            ..Options::default()
+        };
+        let mut output = Flushes::default();
+        Interpreter::<u8>::new(options)
+            .run(&tokens, &mut &b"ab"[..], &mut output)
+            .unwrap();
+        assert_eq!(output.flushes, expected, "{flush:?}");
+        // The policy is kept by snapshots
+        let snapshot = Interpreter::<u8>::new(options).snapshot();
+        let mut output = Flushes::default();
+        Interpreter::<u8>::restore(&snapshot)
+            .unwrap()
+            .execute(&lower(&tokens), &mut &b"ab"[..], &mut output)
+            .unwrap();
+        assert_eq!(output.flushes, expected, "{flush:?}");
+    }
+}
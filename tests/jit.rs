@@ -0,0 +1,125 @@
+//! The native code must behave like the interpreter and fail on the same token.
+#![cfg(feature = "jit")]
+
+use mindfuck::{
+    lower, optimize, parse, EdgePolicy, EofPolicy, Interpreter, Options, OutputMode, Tape,
+};
+
+const PROGRAMS: [&str; 4] = [
+    include_str!("../benches/programs/hello.b"),
+    include_str!("../benches/programs/loops.b"),
+    include_str!("../benches/programs/hanoi.b"),
+    include_str!("../benches/programs/mandelbrot.b"),
+];
+
+/// Output and final data pointer of a program, or its error message.
+fn output(source: &str, options: Options, input: &[u8], jit: bool) -> (Vec<u8>, usize, String) {
+    let tokens = optimize(parse(source).unwrap(), &options);
+    let mut interpreter = Interpreter::<u8>::new(options);
+    let mut output = Vec::new();
+    let result = if jit {
+        interpreter.run_jit(&tokens, &mut &input[..], &mut output)
+    } else {
+        interpreter.execute(&lower(&tokens), &mut &input[..], &mut output)
+    };
+    let error = result.err().map(|e| e.to_string()).unwrap_or_default();
+    (output, interpreter.dp() as usize, error)
+}
+
+/// Output, error message and error span of a program.
+fn run(source: &str, options: Options, jit: bool) -> (Vec<u8>, String, Option<(usize, usize)>) {
+    let tokens = optimize(parse(source).unwrap(), &options);
+    let mut interpreter = Interpreter::<u8>::new(options);
+    let mut output = Vec::new();
+    let result = if jit {
+        interpreter.run_jit(&tokens, &mut &b""[..], &mut output)
+    } else {
+        interpreter.execute(&lower(&tokens), &mut &b""[..], &mut output)
+    };
+    let error = result.expect_err(source);
+    let span = error.span().map(|span| (span.start, span.end));
+    (output, error.inner().to_string(), span)
+}
+
+#[test]
+fn errors_are_located() {
+    let options = Options {
+        tape: Tape::Fixed(4),
+        edge: EdgePolicy::Error,
+        ..Options::default()
+    };
+    for source in [
+        "+.<",
+        "+.>>>>.",
+        "+++[>+++<-]>>>>>+",
+        "+>+>+>+[.<]",
+        "++[->>>>>+<<<<<]",
+    ] {
+        let expected = run(source, options, false);
+        assert!(expected.2.is_some(), "{source:?}");
+        assert_eq!(run(source, options, true), expected, "{source:?}");
+    }
+}
+
+#[test]
+fn samples_run_the_same() {
+    for source in PROGRAMS {
+        let expected = output(source, Options::default(), b"", false);
+        assert_eq!(expected.2, "");
+        assert_eq!(output(source, Options::default(), b"", true), expected);
+    }
+}
+
+#[test]
+fn eof_and_output_modes_run_the_same() {
+    for eof in [EofPolicy::Zero, EofPolicy::MinusOne, EofPolicy::Unchanged] {
+        for mode in [OutputMode::Byte, OutputMode::Utf8, OutputMode::Decimal] {
+            let options = Options {
+                eof,
+                output: mode,
+                ..Options::default()
+            };
+            for (source, input) in [
+                (",.,.,.", &b"a"[..]),
+                ("+++,.", b""),
+                (",.,.,.,.", "h\u{e9}!".as_bytes()),
+                ("-.>++++++++[<----->-]<.>,+.", b""),
+            ] {
+                let expected = output(source, options, input, false);
+                assert_eq!(output(source, options, input, true), expected, "{source:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn data_pointer_is_kept_on_error() {
+    let options = Options {
+        tape: Tape::Fixed(4),
+        edge: EdgePolicy::Error,
+        ..Options::default()
+    };
+    for source in [">>+<<<", ">>>>", "+[>+]", ">>+[<+]"] {
+        let expected = output(source, options, b"", false);
+        assert_ne!(expected.2, "", "{source:?}");
+        assert_eq!(output(source, options, b"", true), expected, "{source:?}");
+    }
+    let mut interpreter = Interpreter::<u8>::new(options);
+    let tokens = optimize(parse(">>.").unwrap(), &options);
+    assert!(interpreter
+        .run_jit(&tokens, &mut &b""[..], &mut Broken)
+        .is_err());
+    assert_eq!(interpreter.dp(), 2);
+}
+
+/// A writer that always fails.
+struct Broken;
+
+impl std::io::Write for Broken {
+    fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+        Err(std::io::ErrorKind::BrokenPipe.into())
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+//! Programs that never end must stop at the limits, even when the optimizer
+//! turns their loops into a single instruction.
+
+use std::time::Duration;
+
+use mindfuck::{
+    lower, optimize, parse, Error, Interpreter, Limits, Machine, Options, Status, Tape,
+};
+
+/// Scans forever: the only cell is never zero, or every zero is filled.
+const ENDLESS_SCANS: &[(&str, Tape)] = &[("+[>]", Tape::Fixed(1)), ("+[[>]+]", Tape::Fixed(64))];
+
+fn options(tape: Tape, limits: Limits) -> Options {
+    Options {
+        tape,
+        limits,
+        ..Options::default()
+    }
+}
+
+/// The error of running the program, both on the tokens and on the bytecode.
+fn errors(source: &str, options: Options) -> [Error; 2] {
+    let tokens = optimize(parse(source).unwrap(), &options);
+    let (input, output) = (&mut &b""[..], &mut Vec::new());
+    let run = Interpreter::<u8>::new(options).run(&tokens, input, output);
+    let execute = Interpreter::<u8>::new(options).execute(&lower(&tokens), input, output);
+    [run.unwrap_err(), execute.unwrap_err()]
+}
+
+#[test]
+fn step_limit_stops_scans() {
+    for &(source, tape) in ENDLESS_SCANS {
+        let limits = Limits {
+            steps: Some(10_000),
+            ..Limits::default()
+        };
+        for error in errors(source, options(tape, limits)) {
+            assert!(
+                matches!(error.inner(), Error::StepLimitExceeded { limit: 10_000 }),
+                "{source:?}: {error}"
+            );
+        }
+    }
+}
+
+#[test]
+fn time_limit_stops_scans() {
+    for &(source, tape) in ENDLESS_SCANS {
+        let limits = Limits {
+            time: Some(Duration::from_millis(50)),
+            ..Limits::default()
+        };
+        for error in errors(source, options(tape, limits)) {
+            assert!(
+                matches!(error.inner(), Error::TimeLimitExceeded { .. }),
+                "{source:?}: {error}"
+            );
+        }
+    }
+}
+
+#[test]
+fn machine_pauses_in_the_middle_of_a_scan() {
+    for &(source, tape) in ENDLESS_SCANS {
+        let options = options(tape, Limits::default());
+        let tokens = optimize(parse(source).unwrap(), &options);
+        let mut machine = Machine::<u8>::new(lower(&tokens), options);
+        for _ in 0..10 {
+            assert_eq!(machine.step(1000).unwrap(), Status::Paused, "{source:?}");
+        }
+    }
+}
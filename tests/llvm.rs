@@ -0,0 +1,162 @@
+//! The LLVM IR backend must behave like the interpreter. Runs the generated
+//! modules with `lli`, the tests pass without checking anything when it is
+//! missing.
+
+use std::{
+    fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use mindfuck::{
+    emit_llvm, optimize, parse, CellWidth, EdgePolicy, EofPolicy, Error, Options, OutputMode,
+    Overflow, Tape,
+};
+
+mod common;
+
+use common::{assert_same, available, INPUT};
+
+/// Before LLVM 15 opaque pointers have to be asked for.
+fn lli_flags() -> Vec<&'static str> {
+    let version = Command::new("lli").arg("--version").output().unwrap();
+    let version = String::from_utf8_lossy(&version.stdout);
+    let major = version
+        .split("version ")
+        .nth(1)
+        .and_then(|version| version.split('.').next())
+        .and_then(|major| major.parse::<u32>().ok());
+    match major {
+        Some(major) if major < 15 => vec!["-opaque-pointers"],
+        _ => vec![],
+    }
+}
+
+/// Output of the module, with its standard error if it failed.
+fn interpret_ir(source: &str, options: Options, name: &str) -> (Vec<u8>, Option<String>) {
+    let tokens = optimize(parse(source).unwrap(), &options);
+    let path: PathBuf =
+        std::env::temp_dir().join(format!("mindfuck-llvm-{}-{name}.ll", std::process::id()));
+    fs::write(&path, emit_llvm(&tokens, &options).unwrap()).unwrap();
+
+    let mut child = Command::new("lli")
+        .args(lli_flags())
+        .arg(&path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // The program can exit without reading all of it
+    let _ = child.stdin.take().unwrap().write_all(INPUT);
+    let output = child.wait_with_output().unwrap();
+    fs::remove_file(&path).unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    assert!(
+        output.status.code().is_some_and(|code| code <= 1),
+        "{source:?} with {options:?}: {stderr}"
+    );
+    (output.stdout, (!output.status.success()).then_some(stderr))
+}
+
+#[test]
+fn edges() {
+    if !available("lli") {
+        return eprintln!("lli not found, skipped");
+    }
+    for (name, edge) in [
+        ("wrap", EdgePolicy::Wrap),
+        ("error", EdgePolicy::Error),
+        ("clamp", EdgePolicy::Clamp),
+    ] {
+        let options = Options {
+            tape: Tape::Fixed(16),
+            edge,
+            ..Options::default()
+        };
+        assert_same(options, name, interpret_ir);
+    }
+}
+
+#[test]
+fn growing_tapes_are_rejected() {
+    let tokens = parse("+.").unwrap();
+    for tape in [Tape::Unbounded, Tape::Bidirectional] {
+        let options = Options {
+            tape,
+            ..Options::default()
+        };
+        assert!(matches!(
+            emit_llvm(&tokens, &options),
+            Err(Error::Unsupported(_))
+        ));
+    }
+}
+
+#[test]
+fn cells_and_overflow() {
+    if !available("lli") {
+        return eprintln!("lli not found, skipped");
+    }
+    for (name, cell, overflow) in [
+        ("u16", CellWidth::U16, Overflow::Wrap),
+        ("u32", CellWidth::U32, Overflow::Wrap),
+        ("saturate", CellWidth::U8, Overflow::Saturate),
+        ("u32-saturate", CellWidth::U32, Overflow::Saturate),
+        ("trap", CellWidth::U8, Overflow::Trap),
+        ("u16-trap", CellWidth::U16, Overflow::Trap),
+    ] {
+        let options = Options {
+            tape: Tape::Fixed(64),
+            cell,
+            overflow,
+            ..Options::default()
+        };
+        assert_same(options, name, interpret_ir);
+    }
+}
+
+#[test]
+fn input_and_output() {
+    if !available("lli") {
+        return eprintln!("lli not found, skipped");
+    }
+    for (name, eof, output, cell) in [
+        ("zero", EofPolicy::Zero, OutputMode::Byte, CellWidth::U8),
+        (
+            "minus-one",
+            EofPolicy::MinusOne,
+            OutputMode::Byte,
+            CellWidth::U8,
+        ),
+        (
+            "unchanged",
+            EofPolicy::Unchanged,
+            OutputMode::Byte,
+            CellWidth::U8,
+        ),
+        (
+            "decimal",
+            EofPolicy::MinusOne,
+            OutputMode::Decimal,
+            CellWidth::U16,
+        ),
+        ("utf8", EofPolicy::Zero, OutputMode::Utf8, CellWidth::U32),
+        (
+            "u8-utf8",
+            EofPolicy::MinusOne,
+            OutputMode::Utf8,
+            CellWidth::U8,
+        ),
+    ] {
+        let options = Options {
+            tape: Tape::Fixed(64),
+            eof,
+            output,
+            cell,
+            ..Options::default()
+        };
+        assert_same(options, name, interpret_ir);
+    }
+}
@@ -0,0 +1,82 @@
+//! A machine runs a program a few instructions at a time, waiting for input
+//! when it has none.
+
+use mindfuck::{lower, parse, EofPolicy, Machine, Options, OutputMode, Status};
+
+/// Echoes its input: `,` `[` `.` `,` `]`, without optimizations.
+fn echo(options: Options) -> Machine {
+    Machine::new(lower(&parse(",[.,]").unwrap()), options)
+}
+
+#[test]
+fn waits_for_input_and_stops_on_budgets() {
+    let mut machine = echo(Options::default());
+    assert_eq!(machine.step(10).unwrap(), Status::NeedsInput);
+    assert_eq!(machine.pc(), 0);
+    // Still nothing to read
+    assert_eq!(machine.step(10).unwrap(), Status::NeedsInput);
+
+    machine.push_input(b"ab");
+    assert_eq!(machine.step(1).unwrap(), Status::Paused);
+    assert_eq!(machine.step(1).unwrap(), Status::Paused);
+    assert_eq!(machine.pc(), 2);
+    assert_eq!(machine.step(1).unwrap(), Status::Output(b"a".to_vec()));
+    // `,` and `]` use the budget before the next `.`
+    assert_eq!(machine.step(2).unwrap(), Status::Paused);
+    assert_eq!(machine.step(10).unwrap(), Status::Output(b"b".to_vec()));
+    assert_eq!(machine.step(10).unwrap(), Status::NeedsInput);
+    assert_eq!(machine.pc(), 3);
+
+    machine.push_input(b"c");
+    assert_eq!(machine.step(10).unwrap(), Status::Output(b"c".to_vec()));
+    assert_eq!(machine.step(10).unwrap(), Status::NeedsInput);
+    machine.close_input();
+    assert_eq!(machine.step(10).unwrap(), Status::Halted);
+    assert_eq!(machine.step(10).unwrap(), Status::Halted);
+    assert_eq!(machine.span(), None);
+}
+
+#[test]
+fn queued_input_is_read_before_the_end() {
+    let mut machine = echo(Options::default());
+    machine.push_input(b"xy");
+    machine.close_input();
+    assert_eq!(machine.step(10).unwrap(), Status::Output(b"x".to_vec()));
+    assert_eq!(machine.step(10).unwrap(), Status::Output(b"y".to_vec()));
+    assert_eq!(machine.step(10).unwrap(), Status::Halted);
+}
+
+#[test]
+fn eof_policies_after_closing() {
+    for (eof, repeated) in [(EofPolicy::MinusOne, 0xff), (EofPolicy::Unchanged, b'z')] {
+        let options = Options {
+            eof,
+            ..Options::default()
+        };
+        let mut machine = echo(options);
+        machine.push_input(b"z");
+        assert_eq!(machine.step(10).unwrap(), Status::Output(b"z".to_vec()));
+        machine.close_input();
+        // The cell stays non zero, so the loop never ends
+        for _ in 0..3 {
+            assert_eq!(machine.step(10).unwrap(), Status::Output(vec![repeated]));
+        }
+        assert_eq!(machine.step(1).unwrap(), Status::Paused);
+    }
+}
+
+#[test]
+fn encoded_output_is_one_chunk() {
+    let options = Options {
+        output: OutputMode::Decimal,
+        ..Options::default()
+    };
+    let mut machine = echo(options);
+    machine.push_input(b"A\xff");
+    machine.close_input();
+    assert_eq!(machine.step(3).unwrap(), Status::Output(b"65\n".to_vec()));
+    assert_eq!(machine.step(1).unwrap(), Status::Paused);
+    assert_eq!(machine.step(1).unwrap(), Status::Paused);
+    assert_eq!(machine.step(1).unwrap(), Status::Output(b"255\n".to_vec()));
+    assert_eq!(machine.step(10).unwrap(), Status::Halted);
+}
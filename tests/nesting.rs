@@ -0,0 +1,74 @@
+//! Deeply nested loops must not overflow the stack, the tests run on a
+//! thread with a small one.
+
+use std::thread;
+
+use mindfuck::{
+    emit_c, emit_llvm, emit_wat, lower, optimize, parse, Interpreter, Options, TokenKind,
+};
+
+const DEPTH: usize = 1_000_000;
+const STACK: usize = 256 * 1024;
+
+/// Enters every loop, clears the cell in the innermost one and prints 1.
+fn nested(depth: usize) -> String {
+    format!("+{}-{}+.", "[".repeat(depth), "]".repeat(depth))
+}
+
+fn on_small_stack(test: impl FnOnce() + Send + 'static) {
+    thread::Builder::new()
+        .stack_size(STACK)
+        .spawn(test)
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+#[test]
+fn parse_optimize_and_run() {
+    on_small_stack(|| {
+        let options = Options::default();
+        let tokens = optimize(parse(&nested(DEPTH)).unwrap(), &options);
+        let mut output = Vec::new();
+        Interpreter::<u8>::new(options)
+            .run(&tokens, &mut &b""[..], &mut output)
+            .unwrap();
+        Interpreter::<u8>::new(options)
+            .execute(&lower(&tokens), &mut &b""[..], &mut output)
+            .unwrap();
+        assert_eq!(output, [1, 1]);
+    });
+}
+
+#[test]
+fn clone_compare_and_drop() {
+    on_small_stack(|| {
+        let tokens = parse(&nested(DEPTH)).unwrap();
+        let copy = tokens.clone();
+        assert_eq!(copy, tokens);
+        let other = parse(&nested(DEPTH).replace('-', "+")).unwrap();
+        assert_ne!(other, tokens);
+        let TokenKind::Loop(body) = &tokens[1].kind else {
+            panic!("{:?} is not a loop", tokens[1].kind);
+        };
+        assert!(format!("{body:?}").starts_with("[Token { kind: Loop([Token { kind: Loop(["));
+    });
+}
+
+#[test]
+fn generate_code() {
+    on_small_stack(|| {
+        let options = Options::default();
+        let depth = DEPTH / 10;
+        let tokens = optimize(parse(&nested(depth)).unwrap(), &options);
+        // The indentation stops growing, so the code stays linear in size
+        for code in [
+            emit_c(&tokens, &options),
+            emit_wat(&tokens, &options).unwrap(),
+            emit_llvm(&tokens, &options).unwrap(),
+        ] {
+            assert!(code.len() < 1000 * depth);
+            assert!(code.lines().all(|line| line.len() < 200));
+        }
+    });
+}
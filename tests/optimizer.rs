@@ -0,0 +1,165 @@
+//! The optimized program must behave like the original one under every set of
+//! options, with the same output and the same error.
+
+use mindfuck::{
+    lower, optimize, parse, Cell, EdgePolicy, Error, Interpreter, Limits, Options, Overflow, Tape,
+};
+
+const STEPS: u64 = 100_000;
+
+/// Output and error of a program, `None` if it ran out of steps.
+fn execute<C: Cell>(source: &str, options: Options, optimized: bool) -> Option<(Vec<u8>, String)> {
+    let mut tokens = parse(source).unwrap();
+    if optimized {
+        tokens = optimize(tokens, &options);
+    }
+    let mut output = Vec::new();
+    let result = Interpreter::<C>::new(options).execute(
+        &lower(&tokens),
+        &mut &b"ab\xff\x00"[..],
+        &mut output,
+    );
+    let error = match result {
+        Ok(()) => String::new(),
+        Err(e) => match e.inner() {
+            Error::StepLimitExceeded { .. } => return None,
+            e => e.to_string(),
+        },
+    };
+    Some((output, error))
+}
+
+fn every_options() -> impl Iterator<Item = Options> {
+    let tapes = [
+        Tape::Fixed(4),
+        Tape::Fixed(300),
+        Tape::Unbounded,
+        Tape::Bidirectional,
+    ];
+    let edges = [EdgePolicy::Wrap, EdgePolicy::Error, EdgePolicy::Clamp];
+    let overflows = [Overflow::Wrap, Overflow::Saturate, Overflow::Trap];
+    tapes.into_iter().flat_map(move |tape| {
+        edges.into_iter().flat_map(move |edge| {
+            overflows.into_iter().map(move |overflow| Options {
+                tape,
+                edge,
+                overflow,
+                limits: Limits {
+                    steps: Some(STEPS),
+                    ..Limits::default()
+                },
+                ..Options::default()
+            })
+        })
+    })
+}
+
+fn assert_equivalent(source: &str) {
+    for options in every_options() {
+        if let Some(expected) = execute::<u8>(source, options, false) {
+            let actual = execute::<u8>(source, options, true);
+            assert_eq!(actual, Some(expected), "{source:?} with {options:?}");
+        }
+        if let Some(expected) = execute::<u16>(source, options, false) {
+            let actual = execute::<u16>(source, options, true);
+            assert_eq!(actual, Some(expected), "{source:?} with {options:?}");
+        }
+    }
+}
+
+#[test]
+fn clear_loops() {
+    for source in [
+        "+++[-].",
+        "+++[+].",
+        "-[-]+.",
+        "[-]-+.",
+        "+[-]-.",
+        ">-[+]<.>.",
+    ] {
+        assert_equivalent(source);
+    }
+}
+
+#[test]
+fn scan_loops() {
+    for source in [
+        "+>+>+<<[>].",
+        ">>+<+<+[<]+.",
+        "+>>+<<[>>]+.",
+        "+[>]",
+        "+[<]",
+        "+>+>+>+>+[<<]-.",
+    ] {
+        assert_equivalent(source);
+    }
+}
+
+#[test]
+fn multiply_loops() {
+    for source in [
+        "++++[->++>+++<<]>.>.",
+        "+++[->+<]>.",
+        "-[->+<]>.",
+        "+++[-<+>]<.",
+        "++[->>>+<<<]>>>.",
+        "+++++[->-<]>.",
+    ] {
+        assert_equivalent(source);
+    }
+}
+
+#[test]
+fn moves_and_increments() {
+    for source in [
+        "<>+.",
+        "><+.",
+        ">>><<<+.",
+        "<<>>+.",
+        ">+<.>.",
+        "-+.",
+        "+-.",
+        "++--.",
+        ">>>>+.<<<<",
+        ",>,<.>.",
+    ] {
+        assert_equivalent(source);
+    }
+}
+
+/// Deterministic pseudo-random programs, with balanced brackets.
+#[test]
+fn random_programs() {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    for _ in 0..200 {
+        let mut source = String::new();
+        let mut depth = 0;
+        for _ in 0..next() % 24 {
+            match next() % 9 {
+                0 => source.push('<'),
+                1 => source.push('>'),
+                2 | 3 => source.push('+'),
+                4 => source.push('-'),
+                5 => source.push('.'),
+                6 => source.push(','),
+                7 => {
+                    source.push('[');
+                    depth += 1;
+                }
+                _ if depth > 0 => {
+                    source.push(']');
+                    depth -= 1;
+                }
+                _ => source.push('-'),
+            }
+        }
+        source.extend(std::iter::repeat_n(']', depth));
+        assert_equivalent(&source);
+    }
+}
@@ -0,0 +1,84 @@
+//! `.` writes the cell as a raw byte, as UTF-8 or in decimal.
+
+use mindfuck::{
+    encoded_len, lower, optimize, parse, Cell, Interpreter, Options, OutputMode, OutputSink,
+};
+
+/// The values written one by one, checking that [`encoded_len`] matches.
+fn sink(mode: OutputMode, values: &[u32]) -> Vec<u8> {
+    let mut output = Vec::new();
+    for &value in values {
+        let mut sink = OutputSink::new(mode, Vec::new());
+        sink.write_cell(value).unwrap();
+        let bytes = sink.into_inner();
+        assert_eq!(encoded_len(mode, value), bytes.len(), "{value:#x}");
+        output.extend(bytes);
+    }
+    output
+}
+
+/// Output of `.` on a cell set to `value`, through the interpreter.
+fn execute<C: Cell>(mode: OutputMode, value: u32) -> Vec<u8> {
+    let options = Options {
+        output: mode,
+        ..Options::default()
+    };
+    let source = format!("{}.", "+".repeat(value as usize));
+    let tokens = optimize(parse(&source).unwrap(), &options);
+    let mut output = Vec::new();
+    Interpreter::<C>::new(options)
+        .execute(&lower(&tokens), &mut &b""[..], &mut output)
+        .unwrap();
+    output
+}
+
+#[test]
+fn bytes_are_written_raw() {
+    assert_eq!(
+        sink(OutputMode::Byte, &[0x41, 0xc8, 0x00, 0xff]),
+        b"A\xc8\x00\xff"
+    );
+    // Wider cells keep their low byte
+    assert_eq!(sink(OutputMode::Byte, &[0x1c8, 0x1f600]), b"\xc8\x00");
+    assert_eq!(execute::<u8>(OutputMode::Byte, 200), [0xc8]);
+    assert_eq!(execute::<u16>(OutputMode::Byte, 0x1c8), [0xc8]);
+}
+
+#[test]
+fn code_points_are_encoded_in_utf8() {
+    assert_eq!(
+        sink(OutputMode::Utf8, &[0x41, 0xc8, 0x20ac, 0x1f600]),
+        "AÈ€😀".as_bytes()
+    );
+    assert_eq!(execute::<u8>(OutputMode::Utf8, 200), "È".as_bytes());
+    assert_eq!(execute::<u16>(OutputMode::Utf8, 0x20ac), "€".as_bytes());
+    assert_eq!(execute::<u32>(OutputMode::Utf8, 0x1f600), "😀".as_bytes());
+}
+
+#[test]
+fn invalid_code_points_are_replaced() {
+    // Surrogates and values past U+10FFFF
+    assert_eq!(
+        sink(OutputMode::Utf8, &[0xd800, 0xdfff, 0x110000, u32::MAX]),
+        "\u{fffd}".repeat(4).as_bytes()
+    );
+    assert_eq!(
+        execute::<u16>(OutputMode::Utf8, 0xd800),
+        "\u{fffd}".as_bytes()
+    );
+    assert_eq!(
+        execute::<u32>(OutputMode::Utf8, 0x110000),
+        "\u{fffd}".as_bytes()
+    );
+}
+
+#[test]
+fn values_are_written_in_decimal() {
+    assert_eq!(
+        sink(OutputMode::Decimal, &[0, 200, 65535, u32::MAX]),
+        b"0\n200\n65535\n4294967295\n"
+    );
+    assert_eq!(execute::<u8>(OutputMode::Decimal, 200), b"200\n");
+    assert_eq!(execute::<u16>(OutputMode::Decimal, 0x1c8), b"456\n");
+    assert_eq!(execute::<u32>(OutputMode::Decimal, 0x110000), b"1114112\n");
+}
@@ -0,0 +1,86 @@
+use mindfuck::{parse, Diagnostic, Error};
+
+fn diagnostics(source: &str) -> Vec<Diagnostic> {
+    match parse(source) {
+        Err(Error::Syntax(diagnostics)) => diagnostics,
+        result => panic!("{source:?} should not parse: {result:?}"),
+    }
+}
+
+#[test]
+fn hints_point_at_the_neighbouring_loops() {
+    let diagnostics = diagnostics("[]\n]+[\n[-]]\n[");
+    let summary: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let hint = diagnostic.hint.as_ref().unwrap();
+            (
+                diagnostic.line,
+                diagnostic.column,
+                diagnostic.message.as_str(),
+                hint.message.as_str(),
+                hint.span.map(|span| span.start),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (
+                2,
+                1,
+                "unmatched `]`",
+                "the `[` at 1:1 is already closed at 1:2",
+                Some(0)
+            ),
+            (
+                4,
+                1,
+                "unmatched `[`",
+                "add a `]` after the end of the loop",
+                None
+            ),
+        ]
+    );
+}
+
+#[test]
+fn hint_of_an_unclosed_loop_points_at_the_last_bracket() {
+    let diagnostics = diagnostics("[+[-]\n[]");
+    let hint = diagnostics[0].hint.as_ref().unwrap();
+    assert_eq!(
+        hint.message,
+        "the last `]`, at 2:2, closes the `[` at 2:1 instead"
+    );
+    assert_eq!(hint.span.map(|span| span.start), Some(7));
+}
+
+#[test]
+fn many_unmatched_brackets() {
+    let source = format!("[-]\n{}{}", "]".repeat(100_000), "[".repeat(100_000));
+    let diagnostics = diagnostics(&source);
+    assert_eq!(diagnostics.len(), 200_000);
+    let last = diagnostics.last().unwrap();
+    assert_eq!((last.line, last.column), (2, 200_000));
+    assert_eq!(
+        last.hint.as_ref().unwrap().message,
+        "add a `]` after the end of the loop"
+    );
+}
+
+#[test]
+fn syntax_errors_report_line_and_column() {
+    let error = parse("+\n\t+]\n\n[->+<]\n  [").unwrap_err();
+    let Error::Syntax(diagnostics) = &error else {
+        panic!("{error:?} is not a syntax error");
+    };
+    let locations: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.line, diagnostic.column, diagnostic.span.start))
+        .collect();
+    assert_eq!(locations, [(2, 3, 4), (5, 3, 16)]);
+    assert_eq!(
+        error.to_string(),
+        "unmatched `]` on line: 2:3, unmatched `[` on line: 5:3"
+    );
+}
@@ -0,0 +1,85 @@
+//! The profile counts every executed instruction and maps the counts back to
+//! the source.
+
+use mindfuck::{
+    lower, optimize, parse, Error, Interpreter, Limits, LoopProfile, Options, Profile, Span,
+};
+
+const SOURCE: &str = "++[>+[-]<-]";
+
+fn profile(options: Options) -> (Profile, Result<(), Error>) {
+    let program = lower(&optimize(parse(SOURCE).unwrap(), &options));
+    Interpreter::<u8>::new(options).profile(&program, &mut &b""[..], &mut Vec::new())
+}
+
+#[test]
+fn counts_by_location_and_loop() {
+    let (profile, result) = profile(Options::default());
+    result.unwrap();
+    assert_eq!(profile.total(), 8);
+    // `>+[-]<` is a single instruction setting the next cell
+    assert_eq!(
+        profile.locations(),
+        [
+            (Span::new(4, 8), 2),
+            (Span::new(9, 10), 2),
+            (Span::new(10, 11), 2),
+            (Span::new(0, 2), 1),
+            (Span::new(2, 3), 1),
+        ]
+    );
+    assert_eq!(
+        profile.loops(),
+        [LoopProfile {
+            span: Span::new(2, 11),
+            entries: 1,
+            iterations: 2,
+            instructions: 4,
+        }]
+    );
+    assert_eq!(
+        profile.folded(SOURCE, "a b;c"),
+        "a_b_c 1\na_b_c;loop@1:3 7\n"
+    );
+}
+
+#[test]
+fn report() {
+    let (profile, _) = profile(Options::default());
+    assert_eq!(
+        profile.report(SOURCE),
+        "\
+Instructions executed: 8
+
+Hot spots:
+         count   share  location   code
+             2  25.00%  1:5        +[-]
+             2  25.00%  1:10       -
+             2  25.00%  1:11       ]
+             1  12.50%  1:1        ++
+             1  12.50%  1:3        [
+
+Loops:
+  instructions   share   iterations    entries  location   code
+             4  50.00%            2          1  1:3        [>+[-]<-]
+"
+    );
+}
+
+#[test]
+fn counts_up_to_a_limit_are_kept() {
+    let options = Options {
+        limits: Limits {
+            steps: Some(5),
+            ..Limits::default()
+        },
+        ..Options::default()
+    };
+    let (profile, result) = profile(options);
+    assert!(matches!(
+        result.unwrap_err().inner(),
+        Error::StepLimitExceeded { limit: 5 }
+    ));
+    assert_eq!(profile.total(), 5);
+    assert_eq!(profile.folded(SOURCE, "root"), "root 1\nroot;loop@1:3 4\n");
+}
@@ -0,0 +1,92 @@
+//! A REPL session keeps its tape between lines and can undo its changes.
+
+use mindfuck::{lower, optimize, parse, unclosed, EdgePolicy, Options, Session, Tape, UNDO_LIMIT};
+
+/// Runs `source` on the session, returning its output.
+fn run(session: &mut Session, source: &str) -> Vec<u8> {
+    let options = Options::default();
+    let program = lower(&optimize(parse(source).unwrap(), &options));
+    let mut output = Vec::new();
+    session.run(&program, &mut &b""[..], &mut output).unwrap();
+    output
+}
+
+fn cells(session: &Session) -> Vec<u8> {
+    (0..4)
+        .map(|position| session.interpreter().cell(position).unwrap())
+        .collect()
+}
+
+#[test]
+fn lines_share_the_tape() {
+    let mut session = Session::new(Options::default());
+    run(&mut session, "+++>");
+    run(&mut session, "++");
+    assert_eq!(run(&mut session, "<[->+<]>."), [5]);
+    assert_eq!(cells(&session), [0, 5, 0, 0]);
+    assert_eq!(session.interpreter().dp(), 1);
+}
+
+#[test]
+fn undo_restores_the_previous_state() {
+    let mut session = Session::new(Options::default());
+    run(&mut session, "+>");
+    run(&mut session, "++");
+    session.set_cell(3, 7).unwrap();
+    assert!(session.undo());
+    assert_eq!(cells(&session), [1, 2, 0, 0]);
+    session.reset();
+    assert_eq!(cells(&session), [0; 4]);
+    assert!(session.undo());
+    assert_eq!(cells(&session), [1, 2, 0, 0]);
+    assert_eq!(session.interpreter().dp(), 1);
+    assert!(session.undo());
+    assert!(session.undo());
+    assert_eq!(cells(&session), [0; 4]);
+    assert!(!session.undo());
+}
+
+#[test]
+fn undo_is_limited() {
+    let mut session = Session::new(Options::default());
+    for _ in 0..UNDO_LIMIT + 5 {
+        run(&mut session, "+");
+    }
+    for _ in 0..UNDO_LIMIT {
+        assert!(session.undo());
+    }
+    assert!(!session.undo());
+    // The oldest changes can't be reverted anymore
+    assert_eq!(cells(&session), [5, 0, 0, 0]);
+}
+
+#[test]
+fn failed_changes_are_kept_or_dropped() {
+    let options = Options {
+        tape: Tape::Fixed(4),
+        edge: EdgePolicy::Error,
+        ..Options::default()
+    };
+    let mut session = Session::new(options);
+    let program = lower(&parse("+<").unwrap());
+    let error = session.run(&program, &mut &b""[..], &mut Vec::new());
+    assert!(error.is_err());
+    assert_eq!(cells(&session), [1, 0, 0, 0]);
+    // A cell outside the tape is not set and leaves nothing to undo
+    assert!(session.set_cell(4, 1).is_err());
+    assert!(session.undo());
+    assert_eq!(cells(&session), [0; 4]);
+    assert!(!session.undo());
+}
+
+#[test]
+fn lines_wait_for_unclosed_loops() {
+    assert!(!unclosed(""));
+    assert!(!unclosed("+[-]>"));
+    assert!(unclosed("+["));
+    assert!(unclosed("[[-]\n>"));
+    assert!(!unclosed("[[-]\n>]"));
+    // A stray `]` can't be fixed by more lines, it is reported right away
+    assert!(!unclosed("]["));
+    assert!(!unclosed("[]]["));
+}
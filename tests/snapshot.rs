@@ -0,0 +1,125 @@
+use mindfuck::{
+    lower, optimize, parse, CellWidth, Error, Interpreter, Machine, Options, Status, Tape,
+    SNAPSHOT_VERSION,
+};
+
+const PROGRAM: &str = concat!(
+    include_str!("../benches/programs/hello.b"),
+    // Echoes the input backwards on the free cells, growing the tape
+    ">>>,[>,]<[.<]",
+);
+const INPUT: &[u8] = b"snapshot";
+
+fn machine(options: Options) -> Machine<u16> {
+    let tokens = optimize(parse(PROGRAM).unwrap(), &options);
+    let mut machine = Machine::new(lower(&tokens), options);
+    machine.push_input(INPUT);
+    machine.close_input();
+    machine
+}
+
+/// Runs the machine to the end, a few instructions at a time, appending the
+/// output.
+fn finish(machine: &mut Machine<u16>, output: &mut Vec<u8>) {
+    loop {
+        match machine.step(7).unwrap() {
+            Status::Output(bytes) => output.extend(bytes),
+            Status::Paused => (),
+            Status::Halted => return,
+            Status::NeedsInput => unreachable!("the input is closed"),
+        }
+    }
+}
+
+fn options() -> Options {
+    Options {
+        tape: Tape::Unbounded,
+        cell: CellWidth::U16,
+        ..Options::default()
+    }
+}
+
+#[test]
+fn restored_machine_continues_the_run() {
+    let mut expected = Vec::new();
+    finish(&mut machine(options()), &mut expected);
+    assert!(expected.ends_with(b"tohspans"));
+
+    // Snapshots after every pause, then finishes from the restored machine
+    let mut machine = machine(options());
+    let mut output = Vec::new();
+    let mut snapshots = 0;
+    loop {
+        let mut restored = Machine::<u16>::restore(&machine.snapshot()).unwrap();
+        assert_eq!(restored.pc(), machine.pc());
+        let mut rest = output.clone();
+        finish(&mut restored, &mut rest);
+        assert_eq!(rest, expected, "restored at {}", machine.pc());
+        snapshots += 1;
+        match machine.step(5).unwrap() {
+            Status::Output(bytes) => output.extend(bytes),
+            Status::Paused => (),
+            Status::Halted => break,
+            Status::NeedsInput => unreachable!("the input is closed"),
+        }
+    }
+    assert_eq!(output, expected);
+    assert!(snapshots > 100);
+}
+
+#[test]
+fn restored_interpreter_keeps_the_tape() {
+    let tokens = optimize(parse("+++>++>+").unwrap(), &options());
+    let more = optimize(parse("<<[.>]").unwrap(), &options());
+    let mut interpreter = Interpreter::<u16>::new(options());
+    interpreter
+        .run(&tokens, &mut &b""[..], &mut Vec::new())
+        .unwrap();
+    let mut restored = Interpreter::<u16>::restore(&interpreter.snapshot()).unwrap();
+    let (mut expected, mut output) = (Vec::new(), Vec::new());
+    interpreter
+        .run(&more, &mut &b""[..], &mut expected)
+        .unwrap();
+    restored.run(&more, &mut &b""[..], &mut output).unwrap();
+    assert_eq!(output, expected);
+    assert_eq!(output, [3, 2, 1]);
+}
+
+#[test]
+fn other_versions_are_rejected() {
+    let mut snapshot = machine(options()).snapshot();
+    assert_eq!(snapshot[4..6], SNAPSHOT_VERSION.to_le_bytes());
+    for version in [0, SNAPSHOT_VERSION + 1, u16::MAX] {
+        snapshot[4..6].copy_from_slice(&version.to_le_bytes());
+        assert!(matches!(
+            Machine::<u16>::restore(&snapshot),
+            Err(Error::InvalidSnapshot("unsupported version"))
+        ));
+    }
+}
+
+#[test]
+fn truncated_snapshots_are_rejected() {
+    let mut machine = machine(options());
+    machine.step(100).unwrap();
+    let snapshot = machine.snapshot();
+    assert!(Machine::<u16>::restore(&snapshot).is_ok());
+    for len in 0..snapshot.len() {
+        assert!(
+            matches!(
+                Machine::<u16>::restore(&snapshot[..len]),
+                Err(Error::InvalidSnapshot(_))
+            ),
+            "restored from {len} of {} bytes",
+            snapshot.len()
+        );
+    }
+
+    let snapshot = Interpreter::<u16>::new(options()).snapshot();
+    for len in 0..snapshot.len() {
+        assert!(matches!(
+            Interpreter::<u16>::restore(&snapshot[..len]),
+            Err(Error::InvalidSnapshot(_))
+        ));
+    }
+}
@@ -0,0 +1,140 @@
+//! The WebAssembly backend must produce valid modules that behave like the
+//! interpreter. Runs them with `node`, the tests pass without checking
+//! anything when it is missing.
+
+use std::{
+    fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use mindfuck::{
+    emit_wasm, optimize, parse, CellWidth, EdgePolicy, EofPolicy, Options, OutputMode, OutputSink,
+    Tape,
+};
+
+mod common;
+
+use common::{assert_same, available, INPUT};
+
+/// Validates the module, then runs it printing every value passed to
+/// `env.output` on its own line. Exits with 2 if the module is invalid and
+/// with 1 if it traps.
+const HOST: &str = r#"
+const fs = require('fs');
+const bytes = fs.readFileSync(process.argv[2]);
+if (!WebAssembly.validate(bytes)) {
+    console.error('invalid module');
+    process.exit(2);
+}
+const input = fs.readFileSync(0);
+let position = 0;
+const values = [];
+const env = {
+    output: value => values.push(value >>> 0),
+    input: () => position < input.length ? input[position++] : -1,
+};
+WebAssembly.instantiate(bytes, { env }).then(({ instance }) => {
+    try {
+        instance.exports.run();
+    } catch (e) {
+        console.error(e.message);
+        process.exitCode = 1;
+    }
+    process.stdout.write(values.map(value => value + '\n').join(''));
+});
+"#;
+
+/// Output of the module, encoded like the interpreter does, with an empty
+/// message if it trapped.
+fn instantiate_and_run(source: &str, options: Options, name: &str) -> (Vec<u8>, Option<String>) {
+    let tokens = optimize(parse(source).unwrap(), &options);
+    let directory = std::env::temp_dir();
+    let path = |extension: &str| -> PathBuf {
+        directory.join(format!(
+            "mindfuck-wasm-{}-{name}{extension}",
+            std::process::id()
+        ))
+    };
+    fs::write(path(".wasm"), emit_wasm(&tokens, &options).unwrap()).unwrap();
+    fs::write(path(".js"), HOST).unwrap();
+
+    let mut child = Command::new("node")
+        .arg(path(".js"))
+        .arg(path(".wasm"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // The program can exit without reading all of it
+    let _ = child.stdin.take().unwrap().write_all(INPUT);
+    let result = child.wait_with_output().unwrap();
+    fs::remove_file(path(".wasm")).unwrap();
+    fs::remove_file(path(".js")).unwrap();
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert_ne!(
+        result.status.code(),
+        Some(2),
+        "{source:?} with {options:?}: {stderr}"
+    );
+
+    let mut output = OutputSink::new(options.output, Vec::new());
+    for line in String::from_utf8(result.stdout).unwrap().lines() {
+        output.write_cell(line.parse().unwrap()).unwrap();
+    }
+    let trapped = !result.status.success();
+    (output.into_inner(), trapped.then(String::new))
+}
+
+#[test]
+fn edges() {
+    if !available("node") {
+        return eprintln!("node not found, skipped");
+    }
+    for (name, edge) in [
+        ("wrap", EdgePolicy::Wrap),
+        ("error", EdgePolicy::Error),
+        ("clamp", EdgePolicy::Clamp),
+    ] {
+        let options = Options {
+            tape: Tape::Fixed(16),
+            edge,
+            ..Options::default()
+        };
+        assert_same(options, name, instantiate_and_run);
+    }
+}
+
+#[test]
+fn cells_input_and_output() {
+    if !available("node") {
+        return eprintln!("node not found, skipped");
+    }
+    for (name, cell, eof, output) in [
+        ("u16", CellWidth::U16, EofPolicy::Zero, OutputMode::Decimal),
+        ("u32", CellWidth::U32, EofPolicy::Zero, OutputMode::Utf8),
+        (
+            "minus-one",
+            CellWidth::U8,
+            EofPolicy::MinusOne,
+            OutputMode::Byte,
+        ),
+        (
+            "unchanged",
+            CellWidth::U16,
+            EofPolicy::Unchanged,
+            OutputMode::Decimal,
+        ),
+    ] {
+        let options = Options {
+            tape: Tape::Fixed(64),
+            cell,
+            eof,
+            output,
+            ..Options::default()
+        };
+        assert_same(options, name, instantiate_and_run);
+    }
+}